@@ -0,0 +1,46 @@
+-- Views that preserve the table shapes of the previous schema version. See
+-- schema-changelog.md.
+
+create view v1_repo as
+select repo_id, name, url
+from repo;
+
+create view v1_description as
+select description_id, summary, body
+from description;
+
+create view v1_protocol as
+select protocol_id, repo_id, name, path, copyright, description_id
+from protocol;
+
+create view v1_interface as
+select interface_id, protocol_id, name, version, description_id
+from interface;
+
+create view v1_enum as
+select enum_id, interface_id, name, since, is_bitfield, description_id
+from enum;
+
+create view v1_entry as
+select entry_id, enum_id, name, value_str, value, summary, since, deprecated_since, description_id
+from entry;
+
+create view v1_message as
+select message_id, interface_id, number, name, is_request, is_destructor, since, deprecated_since, description_id
+from message;
+
+create view v1_type as
+select type_id, name
+from type;
+
+create view v1_arg as
+select arg_id, message_id, position, name, type_id, summary, description_id, interface_name, allow_null, enum_name
+from arg;
+
+create view v1_rel_arg_interface as
+select arg_id, interface_id
+from rel_arg_interface;
+
+create view v1_rel_arg_enum as
+select arg_id, enum_id
+from rel_arg_enum;
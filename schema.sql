@@ -1,10 +1,29 @@
+create table generator
+(
+    version     text   not null,
+    timestamp   bigint not null,
+    fingerprint text   not null
+);
+
 create table repo
 (
     repo_id bigint primary key,
-    name    text not null,
+    name    text not null unique,
     url     text not null
 );
 
+create table file
+(
+    file_id bigint primary key,
+    repo_id bigint not null references repo,
+    path    text   not null,
+    size    bigint not null check (size >= 0),
+    hash    blob   not null,
+    unique (repo_id, path)
+);
+
+create index file_repo_id on file (repo_id);
+
 create table description
 (
     description_id bigint primary key,
@@ -16,21 +35,28 @@ create table protocol
 (
     protocol_id    bigint primary key,
     repo_id        bigint not null references repo,
+    file_id        bigint not null references file,
     name           text   not null,
     path           text   not null,
     copyright      text,
-    description_id bigint references description
+    description_id bigint references description,
+    unique (repo_id, name)
 );
 
 create index protocol_repo_id on protocol (repo_id);
 
+create index protocol_file_id on protocol (file_id);
+
 create table interface
 (
     interface_id   bigint primary key,
-    protocol_id    bigint not null references protocol,
-    name           text   not null,
-    version        bigint not null,
-    description_id bigint references description
+    protocol_id    bigint  not null references protocol,
+    name           text    not null,
+    version        bigint  not null check (version >= 1),
+    description_id bigint references description,
+    is_global      boolean not null default false,
+    global_reason  text    not null default '',
+    unique (protocol_id, name)
 );
 
 create index interface_protocol_id on interface (protocol_id);
@@ -40,9 +66,10 @@ create table enum
     enum_id        bigint primary key,
     interface_id   bigint  not null references interface,
     name           text    not null,
-    since          bigint,
+    since          bigint check (since >= 0),
     is_bitfield    boolean not null,
-    description_id bigint references description
+    description_id bigint references description,
+    unique (interface_id, name)
 );
 
 create index enum_interface_id on enum (interface_id);
@@ -55,9 +82,10 @@ create table entry
     value_str        text   not null,
     value            bigint not null,
     summary          text,
-    since            bigint,
-    deprecated_since bigint,
-    description_id   bigint references description
+    since            bigint check (since >= 0),
+    deprecated_since bigint check (deprecated_since >= 0 and deprecated_since >= since),
+    description_id   bigint references description,
+    unique (enum_id, name)
 );
 
 create index entry_enum_id on entry (enum_id);
@@ -66,13 +94,16 @@ create table message
 (
     message_id       bigint primary key,
     interface_id     bigint  not null references interface,
-    number           bigint  not null,
+    number           bigint  not null check (number >= 0),
     name             text    not null,
     is_request       boolean not null,
     is_destructor    boolean not null,
-    since            bigint,
-    deprecated_since bigint,
-    description_id   bigint references description
+    since            bigint check (since >= 0),
+    deprecated_since bigint check (deprecated_since >= 0 and deprecated_since >= since),
+    description_id   bigint references description,
+    signature        text    not null,
+    unique (interface_id, is_request, number),
+    unique (interface_id, is_request, name)
 );
 
 create index message_interface_id on message (interface_id);
@@ -80,31 +111,48 @@ create index message_interface_id on message (interface_id);
 create table type
 (
     type_id bigint primary key,
-    name    text not null
+    name    text not null unique
 );
 
 create table arg
 (
     arg_id         bigint primary key,
     message_id     bigint  not null references message,
-    position       bigint  not null,
+    position       bigint  not null check (position >= 0),
     name           text    not null,
     type_id        bigint  not null references type,
     summary        text,
     description_id bigint references description,
     interface_name text,
     allow_null     boolean not null,
-    enum_name      text
+    enum_name      text,
+    unique (message_id, position),
+    unique (message_id, name)
 );
 
 create index arg_message_id on arg (message_id);
 
 create index arg_type_id on arg (type_id);
 
+create table wire_arg
+(
+    message_id bigint  not null references message,
+    position   bigint  not null check (position >= 0),
+    arg_id     bigint  not null references arg,
+    type_id    bigint  not null references type,
+    allow_null boolean not null,
+    unique (message_id, position)
+);
+
+create index wire_arg_arg_id on wire_arg (arg_id);
+
+create index wire_arg_type_id on wire_arg (type_id);
+
 create table rel_arg_interface
 (
     arg_id       bigint not null references arg,
-    interface_id bigint not null references interface
+    interface_id bigint not null references interface,
+    unique (arg_id, interface_id)
 );
 
 create index rel_arg_interface_arg_id on rel_arg_interface (arg_id);
@@ -114,9 +162,47 @@ create index rel_arg_interface_interface_id on rel_arg_interface (interface_id);
 create table rel_arg_enum
 (
     arg_id  bigint not null references arg,
-    enum_id bigint not null references enum
+    enum_id bigint not null references enum,
+    unique (arg_id, enum_id)
 );
 
 create index rel_arg_enum_arg_id on rel_arg_enum (arg_id);
 
 create index rel_arg_enum_enum_id on rel_arg_enum (enum_id);
+
+create table object_factory
+(
+    parent_interface_id bigint not null references interface,
+    message_id          bigint not null references message,
+    arg_id              bigint not null references arg,
+    child_interface_id  bigint not null references interface,
+    since               bigint not null check (since >= 1),
+    unique (arg_id, child_interface_id)
+);
+
+create index object_factory_parent_interface_id on object_factory (parent_interface_id);
+
+create index object_factory_message_id on object_factory (message_id);
+
+create index object_factory_child_interface_id on object_factory (child_interface_id);
+
+create table lint_rule
+(
+    lint_rule_id bigint primary key,
+    name         text not null unique,
+    severity     text not null check (severity in ('error', 'warning', 'info')),
+    description  text not null
+);
+
+create table lint_finding
+(
+    lint_finding_id bigint primary key,
+    lint_rule_id    bigint not null references lint_rule,
+    file_id         bigint not null references file,
+    location        text   not null,
+    message         text   not null
+);
+
+create index lint_finding_lint_rule_id on lint_finding (lint_rule_id);
+
+create index lint_finding_file_id on lint_finding (file_id);
@@ -0,0 +1,313 @@
+use {
+    crate::objects::{
+        Index, Objects, Resolved, bind_warning, entry_warnings, format_enum, message_warnings,
+    },
+    clap::Args,
+    regex::Regex,
+    std::{
+        fmt::Write as _,
+        fs::File,
+        io::{self, BufRead, BufReader, Write},
+        path::PathBuf,
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, ArgType, Message, MessageType},
+        db::{Database, DbError},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum AnnotateError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("could not read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("could not read from stdin")]
+    ReadStdin(#[source] io::Error),
+    #[error("could not write to stdout")]
+    WriteStdout(#[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct AnnotateArgs {
+    /// The database to read the protocols from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The log was written by a server. `->` marks events instead of requests.
+    #[clap(long)]
+    server: bool,
+    /// The repos whose interfaces are used if several repos contain an
+    /// interface with the same name.
+    #[clap(long, default_values = ["wayland", "wayland-protocols"])]
+    prefer: Vec<String>,
+    /// The log file (default: stdin).
+    input: Option<PathBuf>,
+}
+
+pub fn main(args: AnnotateArgs) -> Result<(), AnnotateError> {
+    let model = Database::open(&args.db)?.load()?;
+    let index = Index::new(&model, &args.prefer);
+    let mut annotator = Annotator {
+        index: &index,
+        objects: Objects::new(&index),
+        server: args.server,
+        line: Regex::new(
+            r"(?x)
+              ^\[\s*[0-9.]+\]\s*
+              (?:\{[^}]*\}\s*)?
+              (?<discarded>discarded\s+)?
+              (?<send>->\s*)?
+              (?<interface>[^\s\#]+)\#(?<id>[0-9]+)\.(?<message>\w+)
+              \((?<args>.*)\)\s*$
+            ",
+        )
+        .unwrap(),
+        out: vec![],
+    };
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).map_err(|e| AnnotateError::Read(path.clone(), e))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut stdout = io::stdout().lock();
+    for line in input.split(b'\n') {
+        let line = line.map_err(|e| match &args.input {
+            Some(path) => AnnotateError::Read(path.clone(), e),
+            None => AnnotateError::ReadStdin(e),
+        })?;
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+        annotator.annotate(line);
+        writeln!(stdout, "{line}").map_err(AnnotateError::WriteStdout)?;
+        for annotation in annotator.out.drain(..) {
+            writeln!(stdout, "    {annotation}").map_err(AnnotateError::WriteStdout)?;
+        }
+    }
+    Ok(())
+}
+
+struct Annotator<'a> {
+    index: &'a Index<'a>,
+    objects: Objects<'a>,
+    server: bool,
+    line: Regex,
+    /// The annotations of the current line.
+    out: Vec<String>,
+}
+
+impl<'a> Annotator<'a> {
+    fn warn(&mut self, msg: String) {
+        self.out.push(format!("warning: {msg}"));
+    }
+
+    fn annotate(&mut self, line: &str) {
+        let Some(captures) = self.line.captures(line) else {
+            return;
+        };
+        let name = &captures["interface"];
+        let Ok(id) = captures["id"].parse::<u32>() else {
+            return;
+        };
+        let message_name = &captures["message"];
+        let is_request = captures.name("send").is_some() != self.server;
+        let args = split_args(&captures["args"]);
+
+        let (version, interface) = self.object(id, name, captures.name("discarded").is_some());
+        let Some(interface) = interface else {
+            self.warn(format!("unknown interface {name}"));
+            return;
+        };
+        let Some(message) = interface.message(message_name, is_request) else {
+            self.warn(format!("{name} has no message {message_name}"));
+            return;
+        };
+
+        let mut header = format!(
+            "{}/{}: {name}#{id}",
+            interface.repo.name, interface.protocol.name
+        );
+        if let Some(version) = version {
+            let _ = write!(header, " v{version}");
+        }
+        let kind = match message.is_request {
+            true => "request",
+            false => "event",
+        };
+        let _ = write!(header, " {kind} {}", message.name);
+        let wire = message.wire_args();
+        if wire.len() != args.len() {
+            self.out.insert(0, format!("{header}({})", args.join(", ")));
+            self.warn(format!(
+                "{name}.{} has {} wire args but the line contains {}",
+                message.name,
+                wire.len(),
+                args.len(),
+            ));
+            return;
+        }
+        let mut formatted = vec![];
+        let mut pos = 0;
+        for arg in &message.args {
+            let n = wire[pos..]
+                .iter()
+                .take_while(|w| w.arg == wire[pos].arg)
+                .count();
+            let value = self.arg(interface, message, arg, &args[pos..pos + n], version);
+            formatted.push(format!("{}={value}", arg.name));
+            pos += n;
+        }
+        self.out
+            .insert(0, format!("{header}({})", formatted.join(", ")));
+
+        if let Some(version) = version {
+            for warning in message_warnings(name, id, message, version) {
+                self.warn(warning);
+            }
+        }
+        if interface.interface.name == "wl_registry"
+            && message.name == "global"
+            && !message.is_request
+            && let Some(global) = args.get(1).map(|a| a.trim_matches('"'))
+            && self.index.find(global).is_none()
+        {
+            self.warn(format!("unknown interface {global}"));
+        }
+        if message.ty == Some(MessageType::Destructor) {
+            self.objects.destroy(id, message);
+        }
+        if interface.interface.name == "wl_display"
+            && message.name == "delete_id"
+            && !message.is_request
+            && let Some(deleted) = args.first().and_then(|a| a.parse().ok())
+            && self.objects.delete(deleted).is_none()
+        {
+            self.warn(format!("delete_id for unknown object #{deleted}"));
+        }
+    }
+
+    /// Returns the version and interface of the object that a line refers to.
+    ///
+    /// Objects that were not created in the log are assumed to exist, unless
+    /// their ID was deleted. Such uses are reported and the ID stays deleted.
+    fn object(
+        &mut self,
+        id: u32,
+        name: &str,
+        discarded: bool,
+    ) -> (Option<u32>, Option<Resolved<'a>>) {
+        if self.objects.get(id).is_none() && self.objects.is_deleted(id) {
+            self.warn(format!("{name}#{id} is used after its ID was deleted"));
+            return (None, self.index.find(name));
+        }
+        let object = match self.objects.get(id) {
+            Some(object) => object,
+            None => self.objects.assume(id, name),
+        };
+        let version = object.version;
+        let interface = match object.name == name {
+            true => object.interface,
+            false => self.index.find(name),
+        };
+        if object.name != name {
+            let msg = format!("#{id} is a {}, not a {name}", object.name);
+            self.warn(msg);
+        } else if object.destroyed.is_some() && !discarded {
+            self.warn(format!("{name}#{id} is used after it was destroyed"));
+        }
+        (version, interface)
+    }
+
+    /// Formats the value of an arg from its wire values and tracks the objects
+    /// that it creates.
+    fn arg(
+        &mut self,
+        interface: Resolved<'a>,
+        message: &Message,
+        arg: &Arg,
+        values: &[&str],
+        version: Option<u32>,
+    ) -> String {
+        let value = values[values.len() - 1];
+        if value == "nil" {
+            if !arg.allow_null {
+                self.warn(format!("{} is null but not nullable", arg.name));
+            }
+            return value.to_string();
+        }
+        match arg.ty {
+            ArgType::NewId => {
+                let Some(id) = object_id(value) else {
+                    return value.to_string();
+                };
+                let (child, version) = match &arg.interface {
+                    Some(child) => (child.as_str(), version),
+                    None => {
+                        let child = values[0].trim_matches('"');
+                        let version = values.get(1).and_then(|v| v.parse().ok());
+                        if let Some(version) = version
+                            && let Some(warning) = bind_warning(self.index, child, version)
+                        {
+                            self.warn(warning);
+                        }
+                        (child, version)
+                    }
+                };
+                if self.index.find(child).is_none() {
+                    self.warn(format!("unknown interface {child}"));
+                }
+                if let Some(warning) = self.objects.create(id, child, version) {
+                    self.warn(warning);
+                }
+                match version {
+                    Some(version) => format!("new {child}#{id} v{version}"),
+                    None => format!("new {child}#{id}"),
+                }
+            }
+            ArgType::Int | ArgType::Uint => {
+                let Some(enum_) = self.index.arg_enum(interface, message, arg) else {
+                    return value.to_string();
+                };
+                let Ok(number) = value.parse::<i64>() else {
+                    return value.to_string();
+                };
+                if let Some(version) = version {
+                    for warning in entry_warnings(enum_, number, version) {
+                        self.warn(warning);
+                    }
+                }
+                format_enum(enum_, number)
+            }
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// Returns the ID of an object formatted as `new id wl_surface#3` or
+/// `wl_surface#3`.
+fn object_id(value: &str) -> Option<u32> {
+    value.rsplit_once('#')?.1.parse().ok()
+}
+
+/// Splits the args of a message on commas outside of strings.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut res = vec![];
+    if args.trim().is_empty() {
+        return res;
+    }
+    let mut in_string = false;
+    let mut start = 0;
+    for (idx, c) in args.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                res.push(args[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(args[start..].trim());
+    res
+}
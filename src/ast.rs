@@ -1,47 +1,106 @@
-use linearize::Linearize;
+use {
+    linearize::Linearize,
+    serde::{Deserialize, Serialize},
+};
 
-#[derive(Debug)]
-pub(crate) struct Protocol {
-    pub(crate) path: String,
-    pub(crate) name: String,
-    pub(crate) copyright: Option<Copyright>,
-    pub(crate) description: Option<Description>,
-    pub(crate) interfaces: Vec<Interface>,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Protocol {
+    /// The path that was passed to [`parse`](crate::parser::parse).
+    pub path: String,
+    pub name: String,
+    pub copyright: Option<Copyright>,
+    pub description: Option<Description>,
+    pub interfaces: Vec<Interface>,
 }
 
-#[derive(Debug)]
-pub(crate) struct Copyright {
-    pub(crate) body: String,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Copyright {
+    /// The text of the element. See [`Description::body`].
+    pub body: String,
 }
 
-#[derive(Debug)]
-pub(crate) struct Description {
-    pub(crate) summary: Option<String>,
-    pub(crate) body: String,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Description {
+    pub summary: Option<String>,
+    /// The text of the element.
+    ///
+    /// [`parse`](crate::parser::parse) returns the text as it appears in the
+    /// file, including indentation. [`Database`](crate::db::Database) returns the
+    /// text that the database stores, which was normalized with
+    /// [`format_ml_text`](crate::text::format_ml_text).
+    pub body: String,
 }
 
-#[derive(Debug)]
-pub(crate) struct Interface {
-    pub(crate) name: String,
-    pub(crate) version: u32,
-    pub(crate) description: Option<Description>,
-    pub(crate) messages: Vec<Message>,
-    pub(crate) enums: Vec<Enum>,
+impl Description {
+    /// Returns the summary of an optional description unless it is missing or
+    /// blank.
+    pub fn summary_of(description: &Option<Self>) -> Option<&str> {
+        description
+            .as_ref()
+            .and_then(|d| d.summary.as_deref())
+            .filter(|s| !s.trim().is_empty())
+    }
 }
 
-#[derive(Debug)]
-pub(crate) struct Arg {
-    pub(crate) name: String,
-    pub(crate) ty: ArgType,
-    pub(crate) summary: Option<String>,
-    pub(crate) description: Option<Description>,
-    pub(crate) interface: Option<String>,
-    pub(crate) allow_null: bool,
-    pub(crate) enum_: Option<String>,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Interface {
+    pub name: String,
+    pub version: u32,
+    pub description: Option<Description>,
+    pub messages: Vec<Message>,
+    pub enums: Vec<Enum>,
+    /// The order of the messages and enums in the file.
+    ///
+    /// This is empty if the interface was not parsed from a file, e.g. if it
+    /// was loaded from the database.
+    #[serde(default)]
+    pub elements: Vec<InterfaceElement>,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Linearize)]
-pub(crate) enum ArgType {
+/// A message or enum of an interface.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceElement {
+    /// The index of the message in [`Interface::messages`].
+    Message(usize),
+    /// The index of the enum in [`Interface::enums`].
+    Enum(usize),
+}
+
+impl Interface {
+    /// Returns the messages that exist at the given version of the interface.
+    ///
+    /// Deprecated messages are included since they can still be sent.
+    pub fn messages_at_version(&self, version: u32) -> impl Iterator<Item = &Message> {
+        self.messages
+            .iter()
+            .filter(move |m| m.since.unwrap_or(1) <= version)
+    }
+
+    /// Returns the requests if `is_request` is true and the events otherwise.
+    pub fn messages_of_kind(&self, is_request: bool) -> impl Iterator<Item = &Message> {
+        self.messages
+            .iter()
+            .filter(move |m| m.is_request == is_request)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Arg {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: ArgType,
+    pub summary: Option<String>,
+    pub description: Option<Description>,
+    pub interface: Option<String>,
+    pub allow_null: bool,
+    #[serde(rename = "enum")]
+    pub enum_: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Linearize, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgType {
     NewId,
     Int,
     Uint,
@@ -52,39 +111,242 @@ pub(crate) enum ArgType {
     Fd,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub(crate) enum MessageType {
+impl ArgType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let ty = match name {
+            "new_id" => ArgType::NewId,
+            "int" => ArgType::Int,
+            "uint" => ArgType::Uint,
+            "fixed" => ArgType::Fixed,
+            "string" => ArgType::String,
+            "object" => ArgType::Object,
+            "array" => ArgType::Array,
+            "fd" => ArgType::Fd,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ArgType::NewId => "new_id",
+            ArgType::Int => "int",
+            ArgType::Uint => "uint",
+            ArgType::Fixed => "fixed",
+            ArgType::String => "string",
+            ArgType::Object => "object",
+            ArgType::Array => "array",
+            ArgType::Fd => "fd",
+        }
+    }
+
+    /// Returns the letter of the type in libwayland signatures.
+    pub fn signature_char(self) -> char {
+        match self {
+            ArgType::NewId => 'n',
+            ArgType::Int => 'i',
+            ArgType::Uint => 'u',
+            ArgType::Fixed => 'f',
+            ArgType::String => 's',
+            ArgType::Object => 'o',
+            ArgType::Array => 'a',
+            ArgType::Fd => 'h',
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageType {
     Destructor,
 }
 
-#[derive(Debug)]
-pub(crate) struct Entry {
-    pub(crate) name: String,
-    pub(crate) value: String,
-    pub(crate) value_i64: i64,
-    pub(crate) summary: Option<String>,
-    pub(crate) since: Option<u32>,
-    pub(crate) deprecated_since: Option<u32>,
-    pub(crate) description: Option<Description>,
-}
-
-#[derive(Debug)]
-pub(crate) struct Enum {
-    pub(crate) name: String,
-    pub(crate) since: Option<u32>,
-    pub(crate) bitfield: bool,
-    pub(crate) description: Option<Description>,
-    pub(crate) entries: Vec<Entry>,
-}
-
-#[derive(Debug)]
-pub(crate) struct Message {
-    pub(crate) name: String,
-    pub(crate) message_id: usize,
-    pub(crate) is_request: bool,
-    pub(crate) ty: Option<MessageType>,
-    pub(crate) since: Option<u32>,
-    pub(crate) deprecated_since: Option<u32>,
-    pub(crate) description: Option<Description>,
-    pub(crate) args: Vec<Arg>,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    /// The value attribute as written in the file.
+    pub value: String,
+    pub value_i64: i64,
+    pub summary: Option<String>,
+    pub since: Option<u32>,
+    pub deprecated_since: Option<u32>,
+    pub description: Option<Description>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Enum {
+    pub name: String,
+    pub since: Option<u32>,
+    pub bitfield: bool,
+    pub description: Option<Description>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Message {
+    pub name: String,
+    /// The opcode of the message, counted separately for requests and events.
+    pub message_id: usize,
+    pub is_request: bool,
+    #[serde(rename = "type")]
+    pub ty: Option<MessageType>,
+    pub since: Option<u32>,
+    pub deprecated_since: Option<u32>,
+    pub description: Option<Description>,
+    pub args: Vec<Arg>,
+}
+
+impl Message {
+    /// Returns the args as they are encoded on the wire.
+    ///
+    /// A `new_id` arg without an `interface` attribute, as in `wl_registry.bind`,
+    /// is preceded by the name and version of the interface.
+    pub fn wire_args(&self) -> Vec<WireArg> {
+        let mut res = vec![];
+        for (idx, arg) in self.args.iter().enumerate() {
+            let allow_null = arg.allow_null
+                && matches!(
+                    arg.ty,
+                    ArgType::String | ArgType::Object | ArgType::NewId | ArgType::Array
+                );
+            let mut push = |ty, allow_null| {
+                res.push(WireArg {
+                    arg: idx,
+                    ty,
+                    allow_null,
+                })
+            };
+            match (arg.ty, &arg.interface) {
+                (ArgType::NewId, None) => {
+                    push(ArgType::String, allow_null);
+                    push(ArgType::Uint, false);
+                    push(ArgType::NewId, false);
+                }
+                _ => push(arg.ty, allow_null),
+            }
+        }
+        res
+    }
+
+    /// Returns the libwayland signature of the message, e.g. `2?on`.
+    ///
+    /// The signature starts with the version of the message if it is greater
+    /// than 1. Nullable args are prefixed with `?`.
+    pub fn signature(&self) -> String {
+        let mut res = String::new();
+        let since = self.since.unwrap_or(1);
+        if since > 1 {
+            res.push_str(&since.to_string());
+        }
+        for arg in self.wire_args() {
+            if arg.allow_null {
+                res.push('?');
+            }
+            res.push(arg.ty.signature_char());
+        }
+        res
+    }
+}
+
+/// An arg as it is encoded on the wire.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WireArg {
+    /// The index of the arg in [`Message::args`].
+    pub arg: usize,
+    pub ty: ArgType,
+    pub allow_null: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ArgType, Message, WireArg},
+        crate::parser::parse,
+        std::path::Path,
+    };
+
+    /// Parses a request of an interface.
+    fn request(xml: &str) -> Message {
+        let input = format!(
+            r#"<protocol name="p"><interface name="i" version="3">{xml}</interface></protocol>"#
+        );
+        let mut protocols = parse(Path::new("test.xml"), input.as_bytes()).unwrap();
+        protocols
+            .pop()
+            .unwrap()
+            .interfaces
+            .pop()
+            .unwrap()
+            .messages
+            .pop()
+            .unwrap()
+    }
+
+    fn wire_arg(arg: usize, ty: ArgType, allow_null: bool) -> WireArg {
+        WireArg {
+            arg,
+            ty,
+            allow_null,
+        }
+    }
+
+    #[test]
+    fn untyped_new_id() {
+        let bind = request(
+            r#"<request name="bind">
+                 <arg name="name" type="uint"/>
+                 <arg name="id" type="new_id"/>
+               </request>"#,
+        );
+        assert_eq!(bind.signature(), "usun");
+        assert_eq!(
+            bind.wire_args(),
+            [
+                wire_arg(0, ArgType::Uint, false),
+                wire_arg(1, ArgType::String, false),
+                wire_arg(1, ArgType::Uint, false),
+                wire_arg(1, ArgType::NewId, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn nullable_untyped_new_id() {
+        let message = request(
+            r#"<request name="r">
+                 <arg name="id" type="new_id" allow-null="true"/>
+               </request>"#,
+        );
+        assert_eq!(message.signature(), "?sun");
+        assert_eq!(
+            message.wire_args(),
+            [
+                wire_arg(0, ArgType::String, true),
+                wire_arg(0, ArgType::Uint, false),
+                wire_arg(0, ArgType::NewId, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn since_and_nullable_args() {
+        let message = request(
+            r#"<request name="r" since="2">
+                 <arg name="surface" type="object" interface="wl_surface" allow-null="true"/>
+                 <arg name="serial" type="uint" allow-null="true"/>
+                 <arg name="id" type="new_id" interface="wl_callback"/>
+                 <arg name="fd" type="fd"/>
+               </request>"#,
+        );
+        assert_eq!(message.signature(), "2?ounh");
+        assert_eq!(
+            message.wire_args(),
+            [
+                wire_arg(0, ArgType::Object, true),
+                wire_arg(1, ArgType::Uint, false),
+                wire_arg(2, ArgType::NewId, false),
+                wire_arg(3, ArgType::Fd, false),
+            ],
+        );
+    }
 }
@@ -0,0 +1,953 @@
+use {
+    clap::ValueEnum,
+    std::fmt::Write,
+    wayland_db::ast::{Arg, ArgType, Enum, Interface, Message, MessageType, Protocol},
+};
+
+/// Appends formatted text to the output of a generator.
+macro_rules! out {
+    ($g:expr, $($arg:tt)*) => {{
+        let _ = write!($g.out, $($arg)*);
+    }};
+}
+
+/// The outputs of `wayland-scanner`.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub(super) enum Mode {
+    ClientHeader,
+    ServerHeader,
+    PrivateCode,
+    PublicCode,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Side {
+    Client,
+    Server,
+}
+
+/// Generates the same output as `wayland-scanner <mode>` for a protocol.
+///
+/// `core_headers` corresponds to `--include-core-only` and `version` is the
+/// scanner version in the first line.
+pub(super) fn generate(
+    protocol: &Protocol,
+    mode: Mode,
+    core_headers: bool,
+    version: &str,
+) -> String {
+    let mut g = Generator {
+        out: String::new(),
+        protocol,
+        null_run_length: 0,
+    };
+    g.null_run_length = protocol
+        .interfaces
+        .iter()
+        .flat_map(|i| &i.messages)
+        .filter(|m| all_null(m))
+        .map(|m| m.args.len())
+        .max()
+        .unwrap_or(0);
+    out!(g, "/* Generated by wayland-scanner {version} */\n\n");
+    match mode {
+        Mode::ClientHeader => g.header(Side::Client, core_headers),
+        Mode::ServerHeader => g.header(Side::Server, core_headers),
+        Mode::PrivateCode => g.code("WL_PRIVATE"),
+        Mode::PublicCode => g.code("WL_EXPORT"),
+    }
+    g.out
+}
+
+struct Generator<'a> {
+    out: String,
+    protocol: &'a Protocol,
+    /// The length of the run of NULLs at the start of the types array that is
+    /// shared by all messages without typed object args.
+    null_run_length: usize,
+}
+
+impl Generator<'_> {
+    fn header(&mut self, side: Side, core_headers: bool) {
+        let p = self.protocol;
+        let upper = p.name.to_ascii_uppercase();
+        let (s, include) = match (side, core_headers) {
+            (Side::Client, false) => ("CLIENT", "wayland-client.h"),
+            (Side::Client, true) => ("CLIENT", "wayland-client-core.h"),
+            (Side::Server, false) => ("SERVER", "wayland-server.h"),
+            (Side::Server, true) => ("SERVER", "wayland-server-core.h"),
+        };
+        out!(
+            self,
+            "#ifndef {upper}_{s}_PROTOCOL_H\n\
+             #define {upper}_{s}_PROTOCOL_H\n\
+             \n\
+             #include <stdint.h>\n\
+             #include <stddef.h>\n\
+             #include \"{include}\"\n\n\
+             #ifdef  __cplusplus\n\
+             extern \"C\" {{\n\
+             #endif\n\n"
+        );
+        if side == Side::Server {
+            out!(self, "struct wl_client;\nstruct wl_resource;\n\n");
+        }
+        self.mainpage_blurb();
+        let mut types = referenced_interfaces(p);
+        types.extend(p.interfaces.iter().map(|i| &*i.name));
+        types.sort_unstable();
+        types.dedup();
+        for name in types {
+            out!(self, "struct {name};\n");
+        }
+        out!(self, "\n");
+        for i in &p.interfaces {
+            let name = &i.name;
+            let upper = name.to_ascii_uppercase();
+            out!(
+                self,
+                "#ifndef {upper}_INTERFACE\n#define {upper}_INTERFACE\n"
+            );
+            out!(self, "/**\n * @page page_iface_{name} {name}\n");
+            if let Some(d) = &i.description {
+                out!(self, " * @section page_iface_{name}_desc Description\n");
+                self.format_text_to_comment(&raw_text(&d.body), false);
+            }
+            out!(
+                self,
+                " * @section page_iface_{name}_api API\n * See @ref iface_{name}.\n */\n"
+            );
+            out!(
+                self,
+                "/**\n * @defgroup iface_{name} The {name} interface\n"
+            );
+            if let Some(d) = &i.description {
+                self.format_text_to_comment(&raw_text(&d.body), false);
+            }
+            out!(self, " */\n");
+            out!(self, "extern const struct wl_interface {name}_interface;\n");
+            out!(self, "#endif\n");
+        }
+        out!(self, "\n");
+        for i in &p.interfaces {
+            self.enumerations(i, side == Side::Server);
+            let requests: Vec<_> = i.messages_of_kind(true).collect();
+            let events: Vec<_> = i.messages_of_kind(false).collect();
+            match side {
+                Side::Server => {
+                    self.structs(&requests, i, side);
+                    self.opcodes(&events, i);
+                    self.opcode_versions(&events, i);
+                    self.opcode_versions(&requests, i);
+                    self.event_wrappers(&events, i);
+                }
+                Side::Client => {
+                    self.structs(&events, i, side);
+                    self.opcodes(&requests, i);
+                    self.opcode_versions(&events, i);
+                    self.opcode_versions(&requests, i);
+                    self.stubs(&requests, i);
+                }
+            }
+        }
+        out!(
+            self,
+            "#ifdef  __cplusplus\n\
+             }}\n\
+             #endif\n\
+             \n\
+             #endif\n"
+        );
+    }
+
+    fn mainpage_blurb(&mut self) {
+        let p = self.protocol;
+        let name = &p.name;
+        out!(self, "/**\n * @page page_{name} The {name} protocol\n");
+        if let Some(d) = &p.description {
+            if let Some(summary) = &d.summary {
+                out!(self, " * {summary}\n *\n");
+            }
+            out!(self, " * @section page_desc_{name} Description\n");
+            self.format_text_to_comment(&raw_text(&d.body), false);
+            out!(self, " *\n");
+        }
+        out!(self, " * @section page_ifaces_{name} Interfaces\n");
+        for i in &p.interfaces {
+            let summary = i
+                .description
+                .as_ref()
+                .and_then(|d| d.summary.as_deref())
+                .unwrap_or("");
+            out!(self, " * - @subpage page_iface_{} - {summary}\n", i.name);
+        }
+        if let Some(copyright) = &p.copyright {
+            out!(self, " * @section page_copyright_{name} Copyright\n");
+            out!(self, " * <pre>\n");
+            self.format_text_to_comment(&raw_text(&copyright.body), false);
+            out!(self, " * </pre>\n");
+        }
+        out!(self, " */\n");
+    }
+
+    fn enumerations(&mut self, i: &Interface, with_validators: bool) {
+        let upper = i.name.to_ascii_uppercase();
+        for e in &i.enums {
+            let enum_upper = e.name.to_ascii_uppercase();
+            out!(self, "#ifndef {upper}_{enum_upper}_ENUM\n");
+            out!(self, "#define {upper}_{enum_upper}_ENUM\n");
+            if let Some(desc) = &e.description {
+                out!(self, "/**\n * @ingroup iface_{}\n", i.name);
+                if let Some(summary) = &desc.summary {
+                    self.format_text_to_comment(summary, false);
+                }
+                self.format_text_to_comment(&raw_text(&desc.body), false);
+                out!(self, " */\n");
+            }
+            out!(self, "enum {}_{} {{\n", i.name, e.name);
+            for entry in &e.entries {
+                let since = entry.since.unwrap_or(1);
+                let desc = &entry.description;
+                if entry.summary.is_some()
+                    || since > 1
+                    || desc.is_some()
+                    || entry.deprecated_since.is_some()
+                {
+                    out!(self, "\t/**\n");
+                    if let Some(summary) = &entry.summary {
+                        out!(self, "\t * {summary}\n");
+                    }
+                    if let Some(desc) = desc {
+                        let summary = desc.summary.as_deref().unwrap_or("(null)");
+                        out!(self, "\t * {summary}\n\t *\n");
+                        self.desc_dump(&desc.body, "\t * ");
+                    }
+                    if since > 1 {
+                        out!(self, "\t * @since {since}\n");
+                    }
+                    if let Some(deprecated_since) = entry.deprecated_since {
+                        out!(
+                            self,
+                            "\t * @deprecated Deprecated since version {deprecated_since}\n"
+                        );
+                    }
+                    out!(self, "\t */\n");
+                }
+                out!(
+                    self,
+                    "\t{upper}_{enum_upper}_{} = {},\n",
+                    entry.name.to_ascii_uppercase(),
+                    entry.value
+                );
+            }
+            out!(self, "}};\n");
+            for entry in &e.entries {
+                let since = entry.since.unwrap_or(1);
+                if since == 1 {
+                    continue;
+                }
+                out!(self, "/**\n * @ingroup iface_{}\n */\n", i.name);
+                out!(
+                    self,
+                    "#define {upper}_{enum_upper}_{}_SINCE_VERSION {since}\n",
+                    entry.name.to_ascii_uppercase()
+                );
+            }
+            out!(self, "#endif /* {upper}_{enum_upper}_ENUM */\n\n");
+            if with_validators {
+                self.validator(i, e);
+            }
+        }
+    }
+
+    fn validator(&mut self, i: &Interface, e: &Enum) {
+        let name = &i.name;
+        let upper = name.to_ascii_uppercase();
+        let enum_name = &e.name;
+        let enum_upper = enum_name.to_ascii_uppercase();
+        out!(self, "#ifndef {upper}_{enum_upper}_ENUM_IS_VALID\n");
+        out!(self, "#define {upper}_{enum_upper}_ENUM_IS_VALID\n");
+        out!(
+            self,
+            "/**\n\
+             \x20* @ingroup iface_{name}\n\
+             \x20* Validate a {name} {enum_name} value.\n\
+             \x20*\n\
+             \x20* @return true on success, false on error.\n\
+             \x20* @ref {name}_{enum_name}\n\
+             \x20*/\n\
+             static inline bool\n\
+             {name}_{enum_name}_is_valid(uint32_t value, uint32_t version) {{\n"
+        );
+        if e.bitfield {
+            out!(self, "\tuint32_t valid = 0;\n");
+            for entry in &e.entries {
+                out!(
+                    self,
+                    "\tif (version >= {})\n\t\tvalid |= {upper}_{enum_upper}_{};\n",
+                    entry.since.unwrap_or(1),
+                    entry.name.to_ascii_uppercase()
+                );
+            }
+            out!(self, "\treturn (value & ~valid) == 0;\n");
+        } else {
+            out!(self, "\tswitch (value) {{\n");
+            for entry in &e.entries {
+                let cast = match entry.value.starts_with('-') {
+                    true => "(uint32_t)",
+                    false => "",
+                };
+                out!(
+                    self,
+                    "\tcase {cast}{upper}_{enum_upper}_{}:\n\t\treturn version >= {};\n",
+                    entry.name.to_ascii_uppercase(),
+                    entry.since.unwrap_or(1)
+                );
+            }
+            out!(self, "\tdefault:\n\t\treturn false;\n\t}}\n");
+        }
+        out!(self, "}}\n");
+        out!(self, "#endif /* {upper}_{enum_upper}_ENUM_IS_VALID */\n\n");
+    }
+
+    fn structs(&mut self, messages: &[&Message], i: &Interface, side: Side) {
+        if messages.is_empty() {
+            return;
+        }
+        let name = &i.name;
+        let kind = match side {
+            Side::Server => "interface",
+            Side::Client => "listener",
+        };
+        out!(
+            self,
+            "/**\n * @ingroup iface_{name}\n * @struct {name}_{kind}\n */\n"
+        );
+        out!(self, "struct {name}_{kind} {{\n");
+        for m in messages {
+            out!(self, "\t/**\n");
+            if let Some(desc) = &m.description {
+                if let Some(summary) = &desc.summary {
+                    out!(self, "\t * {summary}\n");
+                }
+                out!(self, "\t *\n");
+                self.desc_dump(&desc.body, "\t * ");
+            }
+            for a in &m.args {
+                if side == Side::Server && a.ty == ArgType::NewId && a.interface.is_none() {
+                    out!(
+                        self,
+                        "\t * @param interface name of the objects interface\n\
+                         \t * @param version version of the objects interface\n"
+                    );
+                }
+                if let Some(summary) = &a.summary {
+                    out!(self, "\t * @param {} {summary}\n", a.name);
+                }
+            }
+            let since = m.since.unwrap_or(1);
+            if since > 1 {
+                out!(self, "\t * @since {since}\n");
+            }
+            if let Some(deprecated_since) = m.deprecated_since {
+                out!(
+                    self,
+                    "\t * @deprecated Deprecated since version {deprecated_since}\n"
+                );
+            }
+            out!(self, "\t */\n");
+            out!(self, "\tvoid (*{})(", m.name);
+            let n = indent(m.name.len() + 17);
+            match side {
+                Side::Server => {
+                    out!(
+                        self,
+                        "struct wl_client *client,\n{n}struct wl_resource *resource"
+                    );
+                }
+                Side::Client => {
+                    out!(self, "void *data,\n{n}struct {name} *{name}");
+                }
+            }
+            for a in &m.args {
+                out!(self, ",\n{n}");
+                match (side, a.ty, &a.interface) {
+                    (Side::Server, ArgType::Object, _) => {
+                        out!(self, "struct wl_resource *");
+                    }
+                    (Side::Server, ArgType::NewId, None) => {
+                        out!(self, "const char *interface, uint32_t version, uint32_t ");
+                    }
+                    (Side::Client, ArgType::Object, None) => {
+                        out!(self, "void *");
+                    }
+                    (Side::Client, ArgType::NewId, interface) => {
+                        out!(
+                            self,
+                            "struct {} *",
+                            interface.as_deref().unwrap_or("(null)")
+                        );
+                    }
+                    _ => self.ty(a),
+                }
+                out!(self, "{}", a.name);
+            }
+            out!(self, ");\n");
+        }
+        out!(self, "}};\n\n");
+        if side == Side::Client {
+            out!(self, "/**\n * @ingroup iface_{name}\n */\n");
+            out!(
+                self,
+                "static inline int\n\
+                 {name}_add_listener(struct {name} *{name},\n\
+                 {}const struct {name}_listener *listener, void *data)\n\
+                 {{\n\
+                 \treturn wl_proxy_add_listener((struct wl_proxy *) {name},\n\
+                 {}(void (**)(void)) listener, data);\n\
+                 }}\n\n",
+                indent(14 + name.len()),
+                indent(37),
+            );
+        }
+    }
+
+    fn opcodes(&mut self, messages: &[&Message], i: &Interface) {
+        if messages.is_empty() {
+            return;
+        }
+        let upper = i.name.to_ascii_uppercase();
+        for (opcode, m) in messages.iter().enumerate() {
+            out!(
+                self,
+                "#define {upper}_{} {opcode}\n",
+                m.name.to_ascii_uppercase()
+            );
+        }
+        out!(self, "\n");
+    }
+
+    fn opcode_versions(&mut self, messages: &[&Message], i: &Interface) {
+        let upper = i.name.to_ascii_uppercase();
+        for m in messages {
+            out!(self, "/**\n * @ingroup iface_{}\n */\n", i.name);
+            out!(
+                self,
+                "#define {upper}_{}_SINCE_VERSION {}\n",
+                m.name.to_ascii_uppercase(),
+                m.since.unwrap_or(1)
+            );
+        }
+        out!(self, "\n");
+    }
+
+    fn event_wrappers(&mut self, messages: &[&Message], i: &Interface) {
+        // libwayland-server provides these functions for wl_display.
+        if i.name == "wl_display" {
+            return;
+        }
+        let name = &i.name;
+        let upper = name.to_ascii_uppercase();
+        for m in messages {
+            out!(
+                self,
+                "/**\n\
+                 \x20* @ingroup iface_{name}\n\
+                 \x20* Sends an {} event to the client owning the resource.\n",
+                m.name
+            );
+            out!(self, " * @param resource_ The client's resource\n");
+            for a in &m.args {
+                if let Some(summary) = &a.summary {
+                    out!(self, " * @param {} {summary}\n", a.name);
+                }
+            }
+            out!(self, " */\n");
+            out!(
+                self,
+                "static inline void\n{name}_send_{}(struct wl_resource *resource_",
+                m.name
+            );
+            for a in &m.args {
+                out!(self, ", ");
+                match a.ty {
+                    ArgType::NewId | ArgType::Object => {
+                        out!(self, "struct wl_resource *");
+                    }
+                    _ => self.ty(a),
+                }
+                out!(self, "{}", a.name);
+            }
+            out!(
+                self,
+                ")\n{{\n\twl_resource_post_event(resource_, {upper}_{}",
+                m.name.to_ascii_uppercase()
+            );
+            for a in &m.args {
+                out!(self, ", {}", a.name);
+            }
+            out!(self, ");\n}}\n\n");
+        }
+    }
+
+    fn stubs(&mut self, messages: &[&Message], i: &Interface) {
+        let name = &i.name;
+        let upper = name.to_ascii_uppercase();
+        out!(self, "/** @ingroup iface_{name} */\n");
+        out!(
+            self,
+            "static inline void\n\
+             {name}_set_user_data(struct {name} *{name}, void *user_data)\n\
+             {{\n\
+             \twl_proxy_set_user_data((struct wl_proxy *) {name}, user_data);\n\
+             }}\n\n"
+        );
+        out!(self, "/** @ingroup iface_{name} */\n");
+        out!(
+            self,
+            "static inline void *\n\
+             {name}_get_user_data(struct {name} *{name})\n\
+             {{\n\
+             \treturn wl_proxy_get_user_data((struct wl_proxy *) {name});\n\
+             }}\n\n"
+        );
+        out!(
+            self,
+            "static inline uint32_t\n\
+             {name}_get_version(struct {name} *{name})\n\
+             {{\n\
+             \treturn wl_proxy_get_version((struct wl_proxy *) {name});\n\
+             }}\n\n"
+        );
+        let has_destroy = messages.iter().any(|m| m.name == "destroy");
+        if !has_destroy && name != "wl_display" {
+            out!(self, "/** @ingroup iface_{name} */\n");
+            out!(
+                self,
+                "static inline void\n\
+                 {name}_destroy(struct {name} *{name})\n\
+                 {{\n\
+                 \twl_proxy_destroy((struct wl_proxy *) {name});\n\
+                 }}\n\n"
+            );
+        }
+        for m in messages {
+            let mut new_ids = m.args.iter().filter(|a| a.ty == ArgType::NewId);
+            let ret = new_ids.next();
+            if new_ids.next().is_some() {
+                eprintln!(
+                    "Request {name}::{} has more than one new_id arg, not emitting stub",
+                    m.name
+                );
+                continue;
+            }
+            out!(self, "/**\n * @ingroup iface_{name}\n");
+            if let Some(desc) = &m.description {
+                self.format_text_to_comment(&raw_text(&desc.body), false);
+            }
+            let since = m.since.unwrap_or(1);
+            if since > 1 {
+                out!(self, " *\n * @since {since}\n");
+            }
+            if let Some(deprecated_since) = m.deprecated_since {
+                out!(
+                    self,
+                    " *\n * @deprecated Deprecated since version {deprecated_since}\n"
+                );
+            }
+            out!(self, " */\n");
+            match ret.map(|r| &r.interface) {
+                Some(None) => out!(self, "static inline void *\n"),
+                Some(Some(interface)) => out!(self, "static inline struct {interface} *\n"),
+                None => out!(self, "static inline void\n"),
+            }
+            out!(self, "{name}_{}(struct {name} *{name}", m.name);
+            for a in &m.args {
+                match (a.ty, &a.interface) {
+                    (ArgType::NewId, None) => {
+                        out!(
+                            self,
+                            ", const struct wl_interface *interface, uint32_t version"
+                        );
+                    }
+                    (ArgType::NewId, Some(_)) => {}
+                    _ => {
+                        out!(self, ", ");
+                        self.ty(a);
+                        out!(self, "{}", a.name);
+                    }
+                }
+            }
+            out!(self, ")\n{{\n\t");
+            if let Some(ret) = ret {
+                out!(self, "struct wl_proxy *{};\n\n\t{} = ", ret.name, ret.name);
+            }
+            out!(
+                self,
+                "wl_proxy_marshal_flags((struct wl_proxy *) {name},\n\t\t\t {upper}_{}",
+                m.name.to_ascii_uppercase()
+            );
+            match ret.map(|r| &r.interface) {
+                Some(Some(interface)) => out!(self, ", &{interface}_interface"),
+                Some(None) => out!(self, ", interface"),
+                None => out!(self, ", NULL"),
+            }
+            match ret.map(|r| &r.interface) {
+                Some(None) => out!(self, ", version"),
+                _ => out!(self, ", wl_proxy_get_version((struct wl_proxy *) {name})"),
+            }
+            let flags = match m.ty {
+                Some(MessageType::Destructor) => "WL_MARSHAL_FLAG_DESTROY",
+                None => "0",
+            };
+            out!(self, ", {flags}");
+            for a in &m.args {
+                match (a.ty, &a.interface) {
+                    (ArgType::NewId, None) => out!(self, ", interface->name, version, NULL"),
+                    (ArgType::NewId, Some(_)) => out!(self, ", NULL"),
+                    _ => out!(self, ", {}", a.name),
+                }
+            }
+            out!(self, ");\n");
+            if let Some(ret) = ret {
+                match &ret.interface {
+                    None => out!(self, "\n\treturn (void *) {};\n", ret.name),
+                    Some(interface) => {
+                        out!(self, "\n\treturn (struct {interface} *) {};\n", ret.name)
+                    }
+                }
+            }
+            out!(self, "}}\n\n");
+        }
+    }
+
+    fn code(&mut self, visibility: &str) {
+        let p = self.protocol;
+        if let Some(copyright) = &p.copyright {
+            self.format_text_to_comment(&raw_text(&copyright.body), true);
+        }
+        out!(
+            self,
+            "#include <stdlib.h>\n\
+             #include <stdint.h>\n\
+             #include \"wayland-util.h\"\n\n"
+        );
+        if visibility == "WL_PRIVATE" {
+            out!(
+                self,
+                "#ifndef __has_attribute\n\
+                 # define __has_attribute(x) 0  /* Compatibility with non-clang compilers. */\n\
+                 #endif\n\n\
+                 #if (__has_attribute(visibility) || defined(__GNUC__) && __GNUC__ >= 4)\n\
+                 #define WL_PRIVATE __attribute__ ((visibility(\"hidden\")))\n\
+                 #else\n\
+                 #define WL_PRIVATE\n\
+                 #endif\n\n"
+            );
+        }
+        let mut types = referenced_interfaces(p);
+        types.sort_unstable();
+        types.dedup();
+        for name in types {
+            out!(self, "extern const struct wl_interface {name}_interface;\n");
+        }
+        out!(self, "\n");
+        out!(
+            self,
+            "static const struct wl_interface *{}_types[] = {{\n",
+            p.name
+        );
+        for _ in 0..self.null_run_length {
+            out!(self, "\tNULL,\n");
+        }
+        // The index of each message in the types array.
+        let mut type_index = self.null_run_length;
+        let mut type_indices = vec![];
+        for i in &p.interfaces {
+            for is_request in [true, false] {
+                for m in i.messages_of_kind(is_request) {
+                    if all_null(m) {
+                        type_indices.push(0);
+                        continue;
+                    }
+                    type_indices.push(type_index);
+                    type_index += m.args.len();
+                    for a in &m.args {
+                        match (a.ty, &a.interface) {
+                            (ArgType::NewId | ArgType::Object, Some(interface)) => {
+                                out!(self, "\t&{interface}_interface,\n");
+                            }
+                            _ => out!(self, "\tNULL,\n"),
+                        }
+                    }
+                }
+            }
+        }
+        out!(self, "}};\n\n");
+        let mut type_indices = type_indices.into_iter();
+        for i in &p.interfaces {
+            let name = &i.name;
+            let requests: Vec<_> = i.messages_of_kind(true).collect();
+            let events: Vec<_> = i.messages_of_kind(false).collect();
+            for (messages, suffix) in [(&requests, "requests"), (&events, "events")] {
+                if messages.is_empty() {
+                    continue;
+                }
+                out!(
+                    self,
+                    "static const struct wl_message {name}_{suffix}[] = {{\n"
+                );
+                for m in messages {
+                    let index = type_indices.next().unwrap_or(0);
+                    out!(
+                        self,
+                        "\t{{ \"{}\", \"{}\", {}_types + {index} }},\n",
+                        m.name,
+                        m.signature(),
+                        p.name
+                    );
+                }
+                out!(self, "}};\n\n");
+            }
+            out!(
+                self,
+                "{visibility} const struct wl_interface {name}_interface = {{\n"
+            );
+            out!(self, "\t\"{name}\", {},\n", i.version);
+            for (messages, suffix) in [(&requests, "requests"), (&events, "events")] {
+                match messages.len() {
+                    0 => out!(self, "\t0, NULL,\n"),
+                    n => out!(self, "\t{n}, {name}_{suffix},\n"),
+                }
+            }
+            out!(self, "}};\n\n");
+        }
+    }
+
+    fn ty(&mut self, a: &Arg) {
+        match a.ty {
+            ArgType::Int | ArgType::Fd => out!(self, "int32_t "),
+            ArgType::NewId | ArgType::Uint => out!(self, "uint32_t "),
+            ArgType::Fixed => out!(self, "wl_fixed_t "),
+            ArgType::String => out!(self, "const char *"),
+            ArgType::Object => match &a.interface {
+                Some(interface) => out!(self, "struct {interface} *"),
+                None => out!(self, "void *"),
+            },
+            ArgType::Array => out!(self, "struct wl_array *"),
+        }
+    }
+
+    /// Writes text as the lines of a comment with the leading whitespace of each
+    /// line removed.
+    fn format_text_to_comment(&mut self, text: &str, standalone_comment: bool) {
+        let mut comment_started = !standalone_comment;
+        let mut lines = text.split('\n').peekable();
+        while let Some(line) = lines.next() {
+            let line = line.trim_start_matches([' ', '\t']);
+            // The text after the last newline is only printed if it is not empty.
+            if lines.peek().is_none() && line.is_empty() {
+                break;
+            }
+            let prefix = match comment_started {
+                true => " *",
+                false => "/*",
+            };
+            let space = match line.is_empty() {
+                true => "",
+                false => " ",
+            };
+            out!(self, "{prefix}{space}{line}\n");
+            comment_started = true;
+        }
+        if comment_started && standalone_comment {
+            out!(self, " */\n\n");
+        }
+    }
+
+    /// Writes text as a comment with the words re-wrapped to fit in 72 columns.
+    fn desc_dump(&mut self, desc: &str, prefix: &str) {
+        let mut col = 0;
+        let star = prefix.find('*').unwrap_or(prefix.len());
+        for c in prefix[..star].bytes() {
+            col = match c {
+                b'\t' => (col + 8) & !7,
+                _ => col + 1,
+            };
+        }
+        self.out.push_str(prefix);
+        let startcol = col;
+        col += prefix.len() - star;
+        let hang = match col - startcol > 2 {
+            true => '\t',
+            false => ' ',
+        };
+        let indent = indent(startcol);
+        let d = desc.as_bytes();
+        let is_space = |c: u8| matches!(c, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r');
+        let mut i = 0;
+        while i < d.len() {
+            let k = i;
+            let mut newlines = 0;
+            while i < d.len() && is_space(d[i]) {
+                if d[i] == b'\n' {
+                    newlines += 1;
+                }
+                i += 1;
+            }
+            if i == d.len() {
+                break;
+            }
+            let j = i;
+            while i < d.len() && !is_space(d[i]) {
+                i += 1;
+            }
+            if newlines > 1 {
+                out!(self, "\n{indent}*");
+            }
+            if newlines > 1 || col + i - j > 72 {
+                out!(self, "\n{indent}*{hang}");
+                col = startcol;
+            }
+            if col > startcol && k > 0 {
+                self.out.push(' ');
+                col += 1;
+            }
+            self.out.push_str(&desc[j..i]);
+            col += i - j;
+        }
+        self.out.push('\n');
+    }
+}
+
+/// Returns whether no arg of a message refers to an interface.
+fn all_null(m: &Message) -> bool {
+    !m.args
+        .iter()
+        .any(|a| matches!(a.ty, ArgType::NewId | ArgType::Object) && a.interface.is_some())
+}
+
+/// Returns the interfaces referenced by object and new_id args, unsorted and
+/// with duplicates.
+fn referenced_interfaces(p: &Protocol) -> Vec<&str> {
+    p.interfaces
+        .iter()
+        .flat_map(|i| &i.messages)
+        .flat_map(|m| &m.args)
+        .filter(|a| matches!(a.ty, ArgType::NewId | ArgType::Object))
+        .filter_map(|a| a.interface.as_deref())
+        .collect()
+}
+
+/// Returns `n / 8` tabs followed by `n % 8` spaces.
+fn indent(n: usize) -> String {
+    let mut s = "\t".repeat(n / 8);
+    s.push_str(&" ".repeat(n % 8));
+    s
+}
+
+/// Restores the line break after the opening tag that the database strips from
+/// descriptions and copyright notices.
+fn raw_text(text: &str) -> String {
+    match text.is_empty() || text.starts_with('\n') {
+        true => text.to_string(),
+        false => format!("\n{text}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Mode, generate},
+        std::{fs, path::Path, process::Command},
+        wayland_db::{ast::Protocol, parser::parse, text::format_ml_text},
+    };
+
+    const FIXTURES: [&str; 2] = ["wayland.xml", "xdg-shell.xml"];
+
+    /// Parses a fixture and normalizes its text the way the database does.
+    fn load(name: &str) -> Protocol {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data")
+            .join(name);
+        let input = fs::read(&path).unwrap();
+        let mut protocol = parse(&path, &input).unwrap().pop().unwrap();
+        if let Some(copyright) = &mut protocol.copyright {
+            copyright.body = format_ml_text(&copyright.body);
+        }
+        let interfaces = &mut protocol.interfaces;
+        let descriptions = protocol
+            .description
+            .iter_mut()
+            .chain(interfaces.iter_mut().flat_map(|i| {
+                let messages = i.messages.iter_mut().flat_map(|m| {
+                    let args = m.args.iter_mut().flat_map(|a| &mut a.description);
+                    m.description.iter_mut().chain(args)
+                });
+                let enums = i.enums.iter_mut().flat_map(|e| {
+                    let entries = e.entries.iter_mut().flat_map(|e| &mut e.description);
+                    e.description.iter_mut().chain(entries)
+                });
+                i.description.iter_mut().chain(messages).chain(enums)
+            }));
+        for description in descriptions {
+            description.body = format_ml_text(&description.body);
+        }
+        protocol
+    }
+
+    #[test]
+    fn untyped_new_id_listener() {
+        let header = generate(&load("wayland.xml"), Mode::ServerHeader, false, "1.23.1");
+        let expected = "\tvoid (*bind)(struct wl_client *client,\n\
+                        \t\t     struct wl_resource *resource,\n\
+                        \t\t     uint32_t name,\n\
+                        \t\t     const char *interface, uint32_t version, uint32_t id);\n";
+        assert!(header.contains(expected));
+    }
+
+    /// Compares the output with that of `wayland-scanner` if it is installed.
+    #[test]
+    fn matches_wayland_scanner() {
+        let Ok(output) = Command::new("wayland-scanner").arg("--version").output() else {
+            eprintln!("wayland-scanner is not installed, skipping the comparison");
+            return;
+        };
+        let version = String::from_utf8_lossy(&output.stderr).to_string()
+            + &String::from_utf8_lossy(&output.stdout);
+        let version = version.split_whitespace().last().unwrap();
+        let modes = [
+            (Mode::ClientHeader, "client-header"),
+            (Mode::ServerHeader, "server-header"),
+            (Mode::PrivateCode, "private-code"),
+        ];
+        for fixture in FIXTURES {
+            let protocol = load(fixture);
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data")
+                .join(fixture);
+            for (mode, arg) in modes {
+                let output = Command::new("wayland-scanner")
+                    .arg(arg)
+                    .stdin(fs::File::open(&path).unwrap())
+                    .output()
+                    .unwrap();
+                assert!(output.status.success(), "wayland-scanner {arg} failed");
+                let expected = String::from_utf8(output.stdout).unwrap();
+                let actual = generate(&protocol, mode, false, version);
+                let mismatch = expected
+                    .lines()
+                    .zip(actual.lines())
+                    .position(|(e, a)| e != a)
+                    .unwrap_or(expected.lines().count().min(actual.lines().count()));
+                assert_eq!(
+                    expected.lines().nth(mismatch),
+                    actual.lines().nth(mismatch),
+                    "{fixture} {arg} differs from wayland-scanner in line {}",
+                    mismatch + 1,
+                );
+                assert_eq!(expected, actual, "{fixture} {arg}");
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use {
+    clap::{Args, Subcommand},
+    std::{
+        fs,
+        io::{self, Write},
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+    wayland_db::db::{Database, DbError, ProtocolRef},
+};
+
+mod c;
+mod rust;
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("there is no protocol {0}")]
+    UnknownProtocol(String),
+    #[error("protocol {0} exists in the repos {1}, use REPO/PROTOCOL to select one")]
+    Ambiguous(String, String),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("could not write to stdout")]
+    WriteStdout(#[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct CodegenArgs {
+    #[clap(subcommand)]
+    language: Language,
+}
+
+#[derive(Subcommand, Debug)]
+enum Language {
+    /// Generate Rust bindings for protocols and their dependencies.
+    Rust(RustArgs),
+    /// Generate C code that is identical to the output of wayland-scanner.
+    C(CArgs),
+}
+
+#[derive(Args, Debug)]
+struct RustArgs {
+    /// The database to read the protocols from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The output file (default: stdout).
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// The protocols to generate, as PROTOCOL or REPO/PROTOCOL.
+    #[clap(required = true)]
+    protocols: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct CArgs {
+    /// The database to read the protocol from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The output file (default: stdout).
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// Include the core headers instead of wayland-client.h or wayland-server.h.
+    #[clap(long)]
+    include_core_only: bool,
+    /// The wayland-scanner version in the first line of the output.
+    #[clap(long, default_value = "1.23.1")]
+    scanner_version: String,
+    /// The kind of file to generate.
+    mode: c::Mode,
+    /// The protocol to generate, as PROTOCOL or REPO/PROTOCOL.
+    protocol: String,
+}
+
+pub fn main(args: CodegenArgs) -> Result<(), CodegenError> {
+    match args.language {
+        Language::Rust(args) => {
+            let db = Database::open(&args.db)?;
+            let mut protocols = vec![];
+            for name in &args.protocols {
+                protocols.push(find_protocol(&db, name)?);
+            }
+            let code = rust::generate(&db, &protocols)?;
+            write_output(args.output.as_deref(), &code)
+        }
+        Language::C(args) => {
+            let db = Database::open(&args.db)?;
+            let protocol = find_protocol(&db, &args.protocol)?;
+            let protocol = db
+                .protocol(protocol.id)?
+                .ok_or_else(|| CodegenError::UnknownProtocol(args.protocol.clone()))?;
+            let code = c::generate(
+                &protocol,
+                args.mode,
+                args.include_core_only,
+                &args.scanner_version,
+            );
+            write_output(args.output.as_deref(), &code)
+        }
+    }
+}
+
+/// Looks up a protocol given as `PROTOCOL` or `REPO/PROTOCOL`.
+fn find_protocol(db: &Database, name: &str) -> Result<ProtocolRef, CodegenError> {
+    if let Some((repo, protocol)) = name.split_once('/') {
+        return db
+            .find_protocol(repo, protocol)?
+            .ok_or_else(|| CodegenError::UnknownProtocol(name.to_string()));
+    }
+    let mut protocols = db.find_protocols(name)?;
+    if protocols.len() > 1 {
+        let repos: Vec<_> = protocols.iter().map(|p| &*p.repo).collect();
+        return Err(CodegenError::Ambiguous(name.to_string(), repos.join(", ")));
+    }
+    protocols
+        .pop()
+        .ok_or_else(|| CodegenError::UnknownProtocol(name.to_string()))
+}
+
+fn write_output(path: Option<&Path>, code: &str) -> Result<(), CodegenError> {
+    match path {
+        Some(path) => fs::write(path, code).map_err(|e| CodegenError::Write(path.to_path_buf(), e)),
+        None => io::stdout()
+            .lock()
+            .write_all(code.as_bytes())
+            .map_err(CodegenError::WriteStdout),
+    }
+}
@@ -0,0 +1,499 @@
+use {
+    crate::codegen::CodegenError,
+    std::{
+        collections::{HashMap, HashSet},
+        fmt::Write,
+    },
+    wayland_db::{
+        ast::{Arg, ArgType, Description, Enum, Interface, Message, Protocol},
+        db::{ArgPath, Database, Model, ProtocolRef},
+    },
+};
+
+/// Generates bindings for the protocols and all protocols they depend on.
+///
+/// The output is a single file that starts with the `wire` module followed by
+/// one module per repo, protocol and interface. Enums with `bitfield="true"`
+/// use the `bitflags` crate.
+pub(super) fn generate(db: &Database, protocols: &[ProtocolRef]) -> Result<String, CodegenError> {
+    let mut selected = HashSet::new();
+    for protocol in protocols {
+        selected.insert((protocol.repo.clone(), protocol.protocol.clone()));
+        for dep in db.walk_dependencies(protocol.id)? {
+            selected.insert((dep.repo, dep.protocol));
+        }
+    }
+    let model = db.load()?;
+    let mut bitfields = HashMap::new();
+    for repo in &model.repos {
+        for protocol in &repo.protocols {
+            for interface in &protocol.interfaces {
+                for enum_ in &interface.enums {
+                    let key = (
+                        repo.name.clone(),
+                        protocol.name.clone(),
+                        interface.name.clone(),
+                        enum_.name.clone(),
+                    );
+                    bitfields.insert(key, enum_.bitfield);
+                }
+            }
+        }
+    }
+    let mut g = Generator {
+        out: String::new(),
+        model: &model,
+        selected: &selected,
+        bitfields: &bitfields,
+    };
+    let _ = writeln!(
+        g.out,
+        "// Generated by wayland-db {}. Do not edit.\n",
+        env!("CARGO_PKG_VERSION"),
+    );
+    g.out.push_str(include_str!("wire.rs.in"));
+    for repo in &model.repos {
+        let protocols: Vec<_> = repo
+            .protocols
+            .iter()
+            .filter(|p| selected.contains(&(repo.name.clone(), p.name.clone())))
+            .collect();
+        if protocols.is_empty() {
+            continue;
+        }
+        g.line(0, "");
+        g.line(0, "#[allow(clippy::all)]");
+        g.line(0, &format!("pub mod {} {{", ident(&repo.name)));
+        for (idx, protocol) in protocols.into_iter().enumerate() {
+            if idx > 0 {
+                g.line(0, "");
+            }
+            g.protocol(&repo.name, protocol);
+        }
+        g.line(0, "}");
+    }
+    Ok(g.out)
+}
+
+struct Generator<'a> {
+    out: String,
+    model: &'a Model,
+    selected: &'a HashSet<(String, String)>,
+    bitfields: &'a HashMap<(String, String, String, String), bool>,
+}
+
+/// The location of the interface module that is being generated.
+#[derive(Copy, Clone)]
+struct Ctx<'a> {
+    repo: &'a str,
+    protocol: &'a Protocol,
+    interface: &'a Interface,
+}
+
+/// The code of an arg in a message struct.
+struct ArgCode {
+    ty: String,
+    write: String,
+    read: String,
+}
+
+impl Generator<'_> {
+    fn line(&mut self, depth: usize, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..depth {
+                self.out.push_str("    ");
+            }
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    fn doc(&mut self, depth: usize, summary: Option<&str>, description: &Option<Description>) {
+        let summary = summary
+            .or(description.as_ref().and_then(|d| d.summary.as_deref()))
+            .filter(|s| !s.trim().is_empty());
+        if let Some(summary) = summary {
+            let summary: Vec<_> = summary.split_whitespace().collect();
+            self.line(depth, &format!("/// {}", summary.join(" ")));
+        }
+        if let Some(body) = description.as_ref().map(|d| &d.body)
+            && !body.trim().is_empty()
+        {
+            if summary.is_some() {
+                self.line(depth, "///");
+            }
+            // Indented lines would otherwise be compiled as doctests.
+            let fence = body
+                .lines()
+                .any(|l| l.starts_with("    ") || l.starts_with('\t'));
+            if fence {
+                self.line(depth, "/// ```text");
+            }
+            for line in body.lines() {
+                match line.trim_end() {
+                    "" => self.line(depth, "///"),
+                    line => self.line(depth, &format!("/// {line}")),
+                }
+            }
+            if fence {
+                self.line(depth, "/// ```");
+            }
+        }
+    }
+
+    fn protocol(&mut self, repo: &str, protocol: &Protocol) {
+        self.doc(1, None, &protocol.description);
+        self.line(1, &format!("pub mod {} {{", ident(&protocol.name)));
+        for (idx, interface) in protocol.interfaces.iter().enumerate() {
+            if idx > 0 {
+                self.line(1, "");
+            }
+            let ctx = Ctx {
+                repo,
+                protocol,
+                interface,
+            };
+            self.interface(ctx);
+        }
+        self.line(1, "}");
+    }
+
+    fn interface(&mut self, ctx: Ctx<'_>) {
+        let interface = ctx.interface;
+        let marker = camel(&interface.name);
+        self.doc(2, None, &interface.description);
+        self.line(2, &format!("pub mod {} {{", ident(&interface.name)));
+        self.line(3, "#[allow(unused_imports)]");
+        self.line(3, "use super::super::super::wire;");
+        self.line(3, "");
+        self.line(3, &format!("pub struct {marker};"));
+        self.line(3, "");
+        self.line(3, &format!("impl wire::Interface for {marker} {{"));
+        self.line(
+            4,
+            &format!("const NAME: &'static str = {:?};", interface.name),
+        );
+        self.line(4, &format!("const VERSION: u32 = {};", interface.version));
+        self.line(3, "}");
+        for is_request in [true, false] {
+            let kind = kind(is_request).to_uppercase();
+            for message in interface.messages_of_kind(is_request) {
+                let name = upper(&message.name);
+                self.line(3, "");
+                self.line(
+                    3,
+                    &format!("pub const {kind}_{name}: u16 = {};", message.message_id),
+                );
+                self.line(
+                    3,
+                    &format!(
+                        "pub const {kind}_{name}_SINCE: u32 = {};",
+                        message.since.unwrap_or(1)
+                    ),
+                );
+            }
+        }
+        for is_request in [true, false] {
+            for message in interface.messages_of_kind(is_request) {
+                self.line(3, "");
+                self.message(ctx, &marker, message);
+            }
+        }
+        for enum_ in &interface.enums {
+            self.line(3, "");
+            self.enum_(enum_);
+        }
+        self.line(2, "}");
+    }
+
+    fn message(&mut self, ctx: Ctx<'_>, marker: &str, message: &Message) {
+        let kind = kind(message.is_request);
+        let name = format!("{}{}", camel(&message.name), camel(kind));
+        let args: Vec<_> = message
+            .args
+            .iter()
+            .map(|a| (field(&a.name), a, self.arg(ctx, message, a)))
+            .collect();
+        self.doc(3, None, &message.description);
+        self.line(3, "#[derive(Debug)]");
+        if args.is_empty() {
+            self.line(3, &format!("pub struct {name};"));
+        } else {
+            self.line(3, &format!("pub struct {name} {{"));
+            for (field, arg, code) in &args {
+                self.doc(4, arg.summary.as_deref(), &arg.description);
+                self.line(4, &format!("pub {field}: {},", code.ty));
+            }
+            self.line(3, "}");
+        }
+        self.line(3, "");
+        self.line(3, &format!("impl wire::Message for {name} {{"));
+        self.line(4, &format!("type Interface = {marker};"));
+        self.line(
+            4,
+            &format!("const NAME: &'static str = {:?};", message.name),
+        );
+        let consts = format!("{}_{}", kind.to_uppercase(), upper(&message.name));
+        self.line(4, &format!("const OPCODE: u16 = {consts};"));
+        self.line(4, &format!("const SINCE: u32 = {consts}_SINCE;"));
+        self.line(4, "");
+        if args.is_empty() {
+            self.line(4, "fn write(self, _w: &mut wire::Writer<'_>) {}");
+            self.line(4, "");
+            self.line(
+                4,
+                "fn read(_r: &mut wire::Reader<'_>) -> Result<Self, wire::Error> {",
+            );
+            self.line(5, "Ok(Self)");
+            self.line(4, "}");
+        } else {
+            self.line(4, "fn write(self, w: &mut wire::Writer<'_>) {");
+            for (field, _, code) in &args {
+                let write = code.write.replace("{}", &format!("self.{field}"));
+                self.line(5, &format!("{write};"));
+            }
+            self.line(4, "}");
+            self.line(4, "");
+            self.line(
+                4,
+                "fn read(r: &mut wire::Reader<'_>) -> Result<Self, wire::Error> {",
+            );
+            self.line(5, "Ok(Self {");
+            for (field, _, code) in &args {
+                self.line(6, &format!("{field}: {},", code.read));
+            }
+            self.line(5, "})");
+            self.line(4, "}");
+        }
+        self.line(3, "}");
+    }
+
+    fn arg(&self, ctx: Ctx<'_>, message: &Message, arg: &Arg) -> ArgCode {
+        let path = ArgPath {
+            repo: ctx.repo.to_string(),
+            protocol: ctx.protocol.name.clone(),
+            interface: ctx.interface.name.clone(),
+            is_request: message.is_request,
+            message: message.name.clone(),
+            arg: arg.name.clone(),
+        };
+        let code = |ty: &str, write: &str, read: &str| ArgCode {
+            ty: ty.to_string(),
+            write: write.to_string(),
+            read: read.to_string(),
+        };
+        match arg.ty {
+            ArgType::Int | ArgType::Uint => {
+                let (method, cast) = match arg.ty {
+                    ArgType::Int => ("int", " as i32"),
+                    _ => ("uint", ""),
+                };
+                let Some((ty, bitfield)) = self.enum_type(ctx, &path) else {
+                    let ty = if cast.is_empty() { "u32" } else { "i32" };
+                    return code(ty, &format!("w.{method}({{}})"), &format!("r.{method}()?"));
+                };
+                let back = if cast.is_empty() { "" } else { " as u32" };
+                match bitfield {
+                    true => code(
+                        &ty,
+                        &format!("w.{method}({{}}.bits(){cast})"),
+                        &format!("{ty}::from_bits_retain(r.{method}()?{back})"),
+                    ),
+                    false => code(
+                        &ty,
+                        &format!("w.{method}({{}}.0{cast})"),
+                        &format!("{ty}(r.{method}()?{back})"),
+                    ),
+                }
+            }
+            ArgType::Fixed => code("wire::Fixed", "w.fixed({})", "r.fixed()?"),
+            ArgType::String => match arg.allow_null {
+                true => code("Option<String>", "w.string({}.as_deref())", "r.string()?"),
+                false => code(
+                    "String",
+                    "w.string(Some({}.as_str()))",
+                    "r.string()?.ok_or(wire::Error::UnexpectedNull)?",
+                ),
+            },
+            ArgType::NewId if arg.interface.is_none() => {
+                code("wire::NewId", "w.new_id(&{})", "r.new_id()?")
+            }
+            ArgType::Object | ArgType::NewId => {
+                let interface = self
+                    .interface_type(ctx, &path)
+                    .unwrap_or_else(|| "wire::AnyInterface".to_string());
+                let ty = format!("wire::ObjectId<{interface}>");
+                match arg.allow_null {
+                    true => code(&format!("Option<{ty}>"), "w.object({})", "r.object()?"),
+                    false => code(
+                        &ty,
+                        "w.object(Some({}))",
+                        "r.object()?.ok_or(wire::Error::UnexpectedNull)?",
+                    ),
+                }
+            }
+            ArgType::Array => code("Vec<u8>", "w.array(&{})", "r.array()?"),
+            ArgType::Fd => code("std::os::fd::OwnedFd", "w.fd({})", "r.fd()?"),
+        }
+    }
+
+    /// Returns the path of the module of an interface relative to the module of
+    /// `ctx.interface`.
+    fn module_path(&self, ctx: Ctx<'_>, repo: &str, protocol: &str, interface: &str) -> String {
+        if repo == ctx.repo && protocol == ctx.protocol.name {
+            return format!("super::{}", ident(interface));
+        }
+        format!(
+            "super::super::super::{}::{}::{}",
+            ident(repo),
+            ident(protocol),
+            ident(interface),
+        )
+    }
+
+    /// Returns the marker type of the interface that an arg refers to.
+    ///
+    /// If the reference resolves to several interfaces, those in the repo of the
+    /// arg are preferred.
+    fn interface_type(&self, ctx: Ctx<'_>, path: &ArgPath) -> Option<String> {
+        let targets = self.model.arg_interfaces.get(path)?;
+        let target = targets
+            .iter()
+            .filter(|t| {
+                self.selected
+                    .contains(&(t.repo.clone(), t.protocol.clone()))
+            })
+            .min_by_key(|t| t.repo != ctx.repo)?;
+        let module = self.module_path(ctx, &target.repo, &target.protocol, &target.interface);
+        Some(format!("{module}::{}", camel(&target.interface)))
+    }
+
+    /// Returns the type of the enum that an arg refers to and whether it is a
+    /// bitfield.
+    fn enum_type(&self, ctx: Ctx<'_>, path: &ArgPath) -> Option<(String, bool)> {
+        let targets = self.model.arg_enums.get(path)?;
+        let target = targets
+            .iter()
+            .filter(|t| {
+                self.selected
+                    .contains(&(t.repo.clone(), t.protocol.clone()))
+            })
+            .min_by_key(|t| t.repo != ctx.repo)?;
+        let key = (
+            target.repo.clone(),
+            target.protocol.clone(),
+            target.interface.clone(),
+            target.enum_.clone(),
+        );
+        let bitfield = *self.bitfields.get(&key)?;
+        let module = self.module_path(ctx, &target.repo, &target.protocol, &target.interface);
+        Some((format!("{module}::{}", camel(&target.enum_)), bitfield))
+    }
+
+    fn enum_(&mut self, enum_: &Enum) {
+        let name = camel(&enum_.name);
+        if enum_.bitfield {
+            self.line(3, "bitflags::bitflags! {");
+            self.doc(4, None, &enum_.description);
+            self.line(4, "#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]");
+            self.line(4, &format!("pub struct {name}: u32 {{"));
+            for entry in &enum_.entries {
+                self.doc(5, entry.summary.as_deref(), &entry.description);
+                self.line(
+                    5,
+                    &format!("const {} = {};", upper(&entry.name), entry.value_i64 as u32),
+                );
+            }
+            self.line(4, "}");
+            self.line(3, "}");
+            return;
+        }
+        self.doc(3, None, &enum_.description);
+        self.line(3, "#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]");
+        self.line(3, &format!("pub struct {name}(pub u32);"));
+        if enum_.entries.is_empty() {
+            return;
+        }
+        self.line(3, "");
+        self.line(3, &format!("impl {name} {{"));
+        for entry in &enum_.entries {
+            self.doc(4, entry.summary.as_deref(), &entry.description);
+            self.line(
+                4,
+                &format!(
+                    "pub const {}: Self = Self({});",
+                    upper(&entry.name),
+                    entry.value_i64 as u32
+                ),
+            );
+        }
+        self.line(3, "}");
+    }
+}
+
+fn kind(is_request: bool) -> &'static str {
+    match is_request {
+        true => "request",
+        false => "event",
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Converts a name to a valid identifier by replacing invalid characters and
+/// prefixing names that start with a digit.
+fn sanitize(name: &str) -> String {
+    let mut res: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if res.is_empty() || res.starts_with(|c: char| c.is_ascii_digit()) {
+        res.insert(0, '_');
+    }
+    res
+}
+
+/// Converts a name to a module name.
+fn ident(name: &str) -> String {
+    let name = sanitize(name);
+    match &*name {
+        "crate" | "self" | "super" | "Self" => format!("{name}_"),
+        n if KEYWORDS.contains(&n) => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+/// Converts a name to a field name.
+fn field(name: &str) -> String {
+    ident(&name.to_lowercase())
+}
+
+/// Converts a name to a constant name.
+fn upper(name: &str) -> String {
+    sanitize(&name.to_uppercase())
+}
+
+/// Converts a snake_case name to a type name.
+fn camel(name: &str) -> String {
+    let mut res = String::new();
+    for part in sanitize(name).split('_').filter(|p| !p.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(c) = chars.next() {
+            res.push(c.to_ascii_uppercase());
+            res.extend(chars);
+        }
+    }
+    if res.is_empty() || res.starts_with(|c: char| c.is_ascii_digit()) {
+        res.insert(0, '_');
+    }
+    res
+}
@@ -1,25 +1,50 @@
 use {
-    crate::{ast::Protocol, parser::parse},
     error_reporter::Report,
     regex::Regex,
+    sha2::{Digest, Sha256},
     std::{
-        path::Path,
+        path::{Path, PathBuf},
         process::{Command, Stdio},
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        thread,
     },
     walkdir::WalkDir,
+    wayland_db::{ast::Protocol, parser::parse},
 };
 
 #[derive(Debug)]
 pub(crate) struct Repo {
     pub(crate) name: &'static str,
     pub(crate) url: String,
-    pub(crate) protocols: Vec<Protocol>,
+    pub(crate) commit_time: Option<i64>,
+    pub(crate) globals: &'static [GlobalOverride],
+    pub(crate) files: Vec<File>,
+}
+
+/// Overrides whether an interface is a global.
+///
+/// By default, interfaces are globals if and only if no `new_id` arg with an
+/// `interface` attribute creates them.
+#[derive(Debug)]
+pub(crate) struct GlobalOverride {
+    pub(crate) interface: &'static str,
+    pub(crate) is_global: bool,
+    pub(crate) reason: &'static str,
+}
+
+#[derive(Debug)]
+pub(crate) struct File {
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    pub(crate) hash: [u8; 32],
+    pub(crate) contents: Vec<u8>,
 }
 
 #[derive(Default)]
 struct Config {
     dir: &'static str,
     exclude: Option<Regex>,
+    globals: &'static [GlobalOverride],
 }
 
 pub(crate) fn collect() -> Vec<Repo> {
@@ -30,6 +55,11 @@ pub(crate) fn collect() -> Vec<Repo> {
         },
         Config {
             dir: "external",
+            globals: &[GlobalOverride {
+                interface: "wl_eglstream",
+                is_global: false,
+                reason: "only defines enums used by wl_eglstream_display",
+            }],
             ..Default::default()
         },
         Config {
@@ -64,10 +94,20 @@ pub(crate) fn collect() -> Vec<Repo> {
                 )
                 .unwrap(),
             ),
+            globals: &[GlobalOverride {
+                interface: "wl_display",
+                is_global: false,
+                reason: "created when the client connects to the compositor",
+            }],
             ..Default::default()
         },
         Config {
             dir: "wayland-protocols",
+            globals: &[GlobalOverride {
+                interface: "wp_image_description_reference_v1",
+                is_global: false,
+                reason: "created by the requests of other protocols",
+            }],
             ..Default::default()
         },
         Config {
@@ -81,14 +121,24 @@ pub(crate) fn collect() -> Vec<Repo> {
     ];
     let repos_dir = Path::new("repos");
     let mut repos = vec![];
+    let mut paths = vec![];
     for config in configs {
         let repo_dir = repos_dir.join(config.dir);
-        let Some(url) = get_url(&repo_dir) else {
+        let Some(url) = git(&repo_dir, &["remote", "get-url", "origin"]) else {
             continue;
         };
-        let mut protocols = vec![];
+        let commit_time =
+            git(&repo_dir, &["log", "-1", "--format=%ct"]).and_then(|t| t.trim().parse().ok());
+        let repo_idx = repos.len();
+        repos.push(Repo {
+            name: config.dir,
+            url,
+            commit_time,
+            globals: config.globals,
+            files: vec![],
+        });
         let dir = repos_dir.join(config.dir);
-        for file in WalkDir::new(&dir) {
+        for file in WalkDir::new(&dir).sort_by_file_name() {
             let file = match file {
                 Ok(f) => f,
                 Err(e) => {
@@ -110,39 +160,115 @@ pub(crate) fn collect() -> Vec<Repo> {
             {
                 continue;
             }
-            let contents = match std::fs::read(file) {
-                Ok(c) => c,
-                Err(e) => {
-                    eprintln!("Could not read {}: {}", file.display(), Report::new(e));
-                    continue;
-                }
-            };
-            let p = match parse(rel_path, &contents) {
-                Ok(c) => c,
-                Err(e) => {
-                    eprintln!("Could not parse {}: {}", file.display(), Report::new(e));
-                    continue;
-                }
-            };
-            protocols.extend(p);
+            paths.push((repo_idx, file.to_path_buf(), path.to_string()));
+        }
+    }
+    let files = par_map(&paths, |(_, file, path)| {
+        let contents = match std::fs::read(file) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", file.display(), Report::new(e));
+                return None;
+            }
+        };
+        Some(File {
+            path: path.clone(),
+            size: contents.len() as u64,
+            hash: Sha256::digest(&contents).into(),
+            contents,
+        })
+    });
+    for ((repo_idx, _, _), file) in paths.iter().zip(files) {
+        if let Some(file) = file {
+            repos[*repo_idx].files.push(file);
         }
-        protocols.sort_by(|p1, p2| p1.name.cmp(&p2.name));
-        repos.push(Repo {
-            name: config.dir,
-            url,
-            protocols,
-        });
     }
     repos
 }
 
-fn get_url(repo_dir: &Path) -> Option<String> {
+/// Returns `paths` with every directory replaced by the `.xml` files in it.
+pub(crate) fn find_xml_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, walkdir::Error> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            let file = entry.path();
+            if entry.file_type().is_file() && file.extension().is_some_and(|e| e == "xml") {
+                files.push(file.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Parses the files of all repos on all available cores.
+///
+/// The returned vector contains the protocols of `files[i]` at index `i`, or
+/// `None` if the file could not be parsed. Parse errors are printed.
+pub(crate) fn parse_files(files: &[(&Repo, &File)]) -> Vec<Option<Vec<Protocol>>> {
+    par_map(files, |(repo, file)| {
+        match parse(Path::new(&file.path), &file.contents) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                eprintln!(
+                    "Could not parse {}/{}: {}",
+                    repo.name,
+                    file.path,
+                    Report::new(e),
+                );
+                None
+            }
+        }
+    })
+}
+
+fn par_map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    let num_threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(items.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+    let mut res: Vec<_> = items.iter().map(|_| None).collect();
+    thread::scope(|s| {
+        let threads: Vec<_> = (0..num_threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut res = vec![];
+                    loop {
+                        let idx = next.fetch_add(1, Relaxed);
+                        let Some(item) = items.get(idx) else {
+                            break;
+                        };
+                        res.push((idx, f(item)));
+                    }
+                    res
+                })
+            })
+            .collect();
+        for thread in threads {
+            for (idx, v) in thread.join().unwrap() {
+                res[idx] = Some(v);
+            }
+        }
+    });
+    res.into_iter().map(|v| v.unwrap()).collect()
+}
+
+fn git(repo_dir: &Path, args: &[&str]) -> Option<String> {
     let child = Command::new("git")
         .arg("-C")
         .arg(repo_dir)
-        .arg("remote")
-        .arg("get-url")
-        .arg("origin")
+        .args(args)
         .stdout(Stdio::piped())
         .spawn();
     let child = match child {
@@ -164,14 +290,19 @@ fn get_url(repo_dir: &Path) -> Option<String> {
         }
     };
     if !output.status.success() {
-        eprintln!("git failed while running in {}", repo_dir.display());
+        eprintln!(
+            "git {} failed while running in {}",
+            args.join(" "),
+            repo_dir.display(),
+        );
         return None;
     }
     match String::from_utf8(output.stdout) {
         Ok(s) => Some(s),
         Err(e) => {
             eprintln!(
-                "git remote in {} is not UTF-8: {}",
+                "output of git {} in {} is not UTF-8: {}",
+                args.join(" "),
                 repo_dir.display(),
                 Report::new(e),
             );
@@ -0,0 +1,342 @@
+use {
+    crate::lint::Severity,
+    clap::Args,
+    std::{fs, io, path::PathBuf},
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, Enum, Interface, Message, Protocol},
+        db::{Database, DbError},
+        parser::{ParserError, parse},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum CompatError {
+    #[error("could not load the baseline")]
+    Db(#[from] DbError),
+    #[error("could not read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("could not parse {}", .0.display())]
+    Parse(PathBuf, #[source] ParserError),
+    #[error("protocol {0} exists in the repos {1}, use --repo to select one")]
+    Ambiguous(String, String),
+    #[error("found {0} incompatible changes")]
+    Incompatible(usize),
+}
+
+#[derive(Args, Debug)]
+pub struct CompatArgs {
+    /// The database that contains the baseline.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The repo that contains the baseline protocols. By default, protocols are
+    /// looked up by name in all repos.
+    #[clap(long)]
+    repo: Option<String>,
+    /// The modified protocol files.
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+struct Change {
+    severity: Severity,
+    /// The path of the element in the format used by the lint command.
+    location: String,
+    message: String,
+}
+
+pub fn main(args: CompatArgs) -> Result<(), CompatError> {
+    let db = Database::open(&args.db)?;
+    let mut errors = 0;
+    for file in &args.files {
+        let contents = fs::read(file).map_err(|e| CompatError::Read(file.clone(), e))?;
+        let protocols = parse(file, &contents).map_err(|e| CompatError::Parse(file.clone(), e))?;
+        for new in &protocols {
+            let Some(old) = find_baseline(&db, args.repo.as_deref(), &new.name)? else {
+                println!("{}: {} is not in the baseline", file.display(), new.name);
+                continue;
+            };
+            for change in compare(&old, new) {
+                if change.severity == Severity::Error {
+                    errors += 1;
+                }
+                println!(
+                    "{}: {} {}: {}",
+                    file.display(),
+                    change.severity.name(),
+                    change.location,
+                    change.message,
+                );
+            }
+        }
+    }
+    if errors > 0 {
+        return Err(CompatError::Incompatible(errors));
+    }
+    Ok(())
+}
+
+fn find_baseline(
+    db: &Database,
+    repo: Option<&str>,
+    name: &str,
+) -> Result<Option<Protocol>, CompatError> {
+    let protocol = match repo {
+        Some(repo) => db.find_protocol(repo, name)?,
+        None => {
+            let mut protocols = db.find_protocols(name)?;
+            if protocols.len() > 1 {
+                let repos: Vec<_> = protocols.iter().map(|p| &*p.repo).collect();
+                return Err(CompatError::Ambiguous(name.to_string(), repos.join(", ")));
+            }
+            protocols.pop()
+        }
+    };
+    match protocol {
+        Some(p) => Ok(db.protocol(p.id)?),
+        None => Ok(None),
+    }
+}
+
+/// Compares a protocol with its baseline according to the compatibility rules
+/// of Wayland.
+fn compare(old: &Protocol, new: &Protocol) -> Vec<Change> {
+    let mut checker = Checker { changes: vec![] };
+    for old_interface in &old.interfaces {
+        let location = format!("{}/{}", old.name, old_interface.name);
+        match new.interfaces.iter().find(|i| i.name == old_interface.name) {
+            Some(new_interface) => checker.interface(&location, old_interface, new_interface),
+            None => checker.error(&location, "the interface was removed".to_string()),
+        }
+    }
+    checker.changes
+}
+
+struct Checker {
+    changes: Vec<Change>,
+}
+
+impl Checker {
+    fn add(&mut self, severity: Severity, location: &str, message: String) {
+        self.changes.push(Change {
+            severity,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, location: &str, message: String) {
+        self.add(Severity::Error, location, message);
+    }
+
+    fn warning(&mut self, location: &str, message: String) {
+        self.add(Severity::Warning, location, message);
+    }
+
+    fn interface(&mut self, location: &str, old: &Interface, new: &Interface) {
+        if new.version < old.version {
+            self.error(
+                location,
+                format!(
+                    "the version was decreased from {} to {}",
+                    old.version, new.version
+                ),
+            );
+        }
+        let mut added = false;
+        for is_request in [true, false] {
+            let kind = match is_request {
+                true => "request",
+                false => "event",
+            };
+            let old_messages: Vec<_> = old.messages_of_kind(is_request).collect();
+            let new_messages: Vec<_> = new.messages_of_kind(is_request).collect();
+            for (opcode, old_message) in old_messages.iter().enumerate() {
+                let location = format!("{location}/{kind}:{}", old_message.name);
+                if let Some(new_message) = new_messages.get(opcode)
+                    && new_message.name == old_message.name
+                {
+                    self.message(&location, old_message, new_message);
+                    continue;
+                }
+                match new_messages.iter().position(|m| m.name == old_message.name) {
+                    Some(new_opcode) => self.error(
+                        &location,
+                        format!("the opcode was changed from {opcode} to {new_opcode}"),
+                    ),
+                    None => self.error(&location, format!("the {kind} was removed")),
+                }
+            }
+            for new_message in new_messages.iter().skip(old_messages.len()) {
+                added = true;
+                if old_messages.iter().any(|m| m.name == new_message.name) {
+                    continue;
+                }
+                let location = format!("{location}/{kind}:{}", new_message.name);
+                if new.version > old.version
+                    && !added_since(new_message.since, old.version, new.version)
+                {
+                    self.error(
+                        &location,
+                        format!(
+                            "the {kind} was added without a since attribute greater than the old \
+                             version {} and at most the new version {}",
+                            old.version, new.version
+                        ),
+                    );
+                }
+            }
+        }
+        for old_enum in &old.enums {
+            let location = format!("{location}/enum:{}", old_enum.name);
+            match new.enums.iter().find(|e| e.name == old_enum.name) {
+                Some(new_enum) => {
+                    self.enum_(&location, old.version, new.version, old_enum, new_enum)
+                }
+                None => self.error(&location, "the enum was removed".to_string()),
+            }
+        }
+        if added && new.version <= old.version {
+            self.error(
+                location,
+                "messages were added but the version was not increased".to_string(),
+            );
+        }
+    }
+
+    fn message(&mut self, location: &str, old: &Message, new: &Message) {
+        if old.ty != new.ty {
+            self.error(location, "the destructor type was changed".to_string());
+        }
+        if old.since != new.since {
+            self.error(
+                location,
+                format!(
+                    "the since attribute was changed from {} to {}",
+                    old.since.unwrap_or(1),
+                    new.since.unwrap_or(1)
+                ),
+            );
+        }
+        if old.args.len() != new.args.len() {
+            self.error(
+                location,
+                format!(
+                    "the number of args was changed from {} to {}",
+                    old.args.len(),
+                    new.args.len()
+                ),
+            );
+            return;
+        }
+        for (old_arg, new_arg) in old.args.iter().zip(&new.args) {
+            let location = format!("{location}/arg:{}", old_arg.name);
+            self.arg(&location, old_arg, new_arg);
+        }
+    }
+
+    fn arg(&mut self, location: &str, old: &Arg, new: &Arg) {
+        if old.ty != new.ty {
+            self.error(
+                location,
+                format!(
+                    "the type was changed from {} to {}",
+                    old.ty.name(),
+                    new.ty.name()
+                ),
+            );
+        }
+        if old.interface != new.interface {
+            self.error(
+                location,
+                format!(
+                    "the interface was changed from {} to {}",
+                    old.interface.as_deref().unwrap_or("none"),
+                    new.interface.as_deref().unwrap_or("none"),
+                ),
+            );
+        }
+        if old.allow_null != new.allow_null {
+            self.error(
+                location,
+                format!(
+                    "allow-null was changed from {} to {}",
+                    old.allow_null, new.allow_null
+                ),
+            );
+        }
+        if old.name != new.name {
+            self.warning(location, format!("the arg was renamed to {}", new.name));
+        }
+        if old.enum_ != new.enum_ {
+            self.warning(
+                location,
+                format!(
+                    "the enum was changed from {} to {}",
+                    old.enum_.as_deref().unwrap_or("none"),
+                    new.enum_.as_deref().unwrap_or("none"),
+                ),
+            );
+        }
+    }
+
+    fn enum_(
+        &mut self,
+        location: &str,
+        old_version: u32,
+        new_version: u32,
+        old: &Enum,
+        new: &Enum,
+    ) {
+        if old.bitfield != new.bitfield {
+            self.error(
+                location,
+                format!(
+                    "bitfield was changed from {} to {}",
+                    old.bitfield, new.bitfield
+                ),
+            );
+        }
+        for old_entry in &old.entries {
+            let location = format!("{location}/entry:{}", old_entry.name);
+            match new.entries.iter().find(|e| e.name == old_entry.name) {
+                Some(new_entry) if new_entry.value_i64 != old_entry.value_i64 => self.error(
+                    &location,
+                    format!(
+                        "the value was changed from {} to {}",
+                        old_entry.value, new_entry.value
+                    ),
+                ),
+                Some(_) => {}
+                None => self.error(&location, "the entry was removed".to_string()),
+            }
+        }
+        for new_entry in &new.entries {
+            if old.entries.iter().any(|e| e.name == new_entry.name) {
+                continue;
+            }
+            let location = format!("{location}/entry:{}", new_entry.name);
+            if new_version <= old_version {
+                self.warning(
+                    &location,
+                    "the entry was added but the version was not increased".to_string(),
+                );
+            } else if !added_since(new_entry.since, old_version, new_version) {
+                self.warning(
+                    &location,
+                    format!(
+                        "the entry was added without a since attribute greater than the old \
+                         version {old_version} and at most the new version {new_version}"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Returns whether the `since` attribute of an added message or entry is one of
+/// the versions that were added to the interface.
+fn added_since(since: Option<u32>, old_version: u32, new_version: u32) -> bool {
+    since.is_some_and(|since| old_version < since && since <= new_version)
+}
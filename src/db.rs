@@ -0,0 +1,758 @@
+//! Typed access to `wayland.db`.
+//!
+//! [`Database`] converts the rows of the database back to the types in
+//! [`ast`](crate::ast) so that users do not depend on the layout of the
+//! tables.
+
+use {
+    crate::ast::{
+        Arg, ArgType, Copyright, Description, Entry, Enum, Interface, Message, MessageType,
+        Protocol,
+    },
+    rusqlite::{Connection, OpenFlags, Row, ToSql},
+    serde::Serialize,
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+};
+
+/// The version of `schema.sql` stored in `pragma user_version`.
+///
+/// This must be incremented once for all schema changes between two published
+/// databases. See `schema-changelog.md`.
+pub const SCHEMA_VERSION: i64 = 2;
+
+/// The oldest schema version that [`Database`] can read.
+///
+/// This is only increased when the queries of [`Database`] stop working with
+/// databases of older versions, e.g. because they use a new table.
+pub const MIN_SCHEMA_VERSION: i64 = 2;
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("could not open {}", .0.display())]
+    Open(PathBuf, #[source] rusqlite::Error),
+    #[error("could not read the schema version")]
+    ReadSchemaVersion(#[source] rusqlite::Error),
+    #[error("the database uses schema version {0} but at least {MIN_SCHEMA_VERSION} is required")]
+    SchemaVersion(i64),
+    #[error("could not load the {0} table")]
+    Query(&'static str, #[source] rusqlite::Error),
+    #[error("unknown arg type {0}")]
+    UnknownArgType(String),
+}
+
+/// A read-only connection to `wayland.db`.
+#[derive(Debug)]
+pub struct Database {
+    db: Connection,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProtocolId(i64);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InterfaceId(i64);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnumId(i64);
+
+/// The contents of wayland.db converted back to the AST.
+#[derive(Debug)]
+pub struct Model {
+    pub repos: Vec<Repo>,
+    /// The interfaces that the `interface` attributes of args resolve to.
+    pub arg_interfaces: HashMap<ArgPath, Vec<InterfaceRef>>,
+    /// The enums that the `enum` attributes of args resolve to.
+    pub arg_enums: HashMap<ArgPath, Vec<EnumRef>>,
+}
+
+#[derive(Debug)]
+pub struct Repo {
+    pub name: String,
+    pub url: String,
+    pub protocols: Vec<Protocol>,
+}
+
+/// Identifies an arg by the names of its ancestors.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ArgPath {
+    pub repo: String,
+    pub protocol: String,
+    pub interface: String,
+    pub is_request: bool,
+    pub message: String,
+    pub arg: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProtocolRef {
+    #[serde(skip)]
+    pub id: ProtocolId,
+    pub repo: String,
+    pub protocol: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InterfaceRef {
+    #[serde(skip)]
+    pub id: InterfaceId,
+    #[serde(skip)]
+    pub protocol_id: ProtocolId,
+    pub repo: String,
+    pub protocol: String,
+    pub interface: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EnumRef {
+    #[serde(skip)]
+    pub id: EnumId,
+    #[serde(skip)]
+    pub interface_id: InterfaceId,
+    pub repo: String,
+    pub protocol: String,
+    pub interface: String,
+    #[serde(rename = "enum")]
+    pub enum_: String,
+}
+
+/// A `new_id` arg that creates an object of another interface.
+#[derive(Clone, Debug, Serialize)]
+pub struct ObjectFactory {
+    /// The interface of the message.
+    pub parent: InterfaceRef,
+    pub is_request: bool,
+    pub message: String,
+    pub arg: String,
+    /// The interface of the created object.
+    pub child: InterfaceRef,
+    /// The version of the parent in which the message was added.
+    pub since: u32,
+}
+
+impl Model {
+    pub fn arg_path(
+        repo: &Repo,
+        protocol: &Protocol,
+        interface: &Interface,
+        message: &Message,
+        arg: &Arg,
+    ) -> ArgPath {
+        ArgPath {
+            repo: repo.name.clone(),
+            protocol: protocol.name.clone(),
+            interface: interface.name.clone(),
+            is_request: message.is_request,
+            message: message.name.clone(),
+            arg: arg.name.clone(),
+        }
+    }
+}
+
+fn description(row: &Row<'_>, idx: usize) -> rusqlite::Result<Option<Description>> {
+    let summary = row.get(idx)?;
+    let body: Option<String> = row.get(idx + 1)?;
+    Ok(body.map(|body| Description { summary, body }))
+}
+
+fn group<T>(rows: Vec<(i64, T)>) -> HashMap<i64, Vec<T>> {
+    let mut res = HashMap::<_, Vec<_>>::new();
+    for (parent, child) in rows {
+        res.entry(parent).or_default().push(child);
+    }
+    res
+}
+
+/// Reads `i.interface_id, i.protocol_id, r.name, p.name, i.name` starting at `idx`.
+fn interface_ref(row: &Row<'_>, idx: usize) -> rusqlite::Result<InterfaceRef> {
+    Ok(InterfaceRef {
+        id: InterfaceId(row.get(idx)?),
+        protocol_id: ProtocolId(row.get(idx + 1)?),
+        repo: row.get(idx + 2)?,
+        protocol: row.get(idx + 3)?,
+        interface: row.get(idx + 4)?,
+    })
+}
+
+/// Reads `e.enum_id, e.interface_id, r.name, p.name, i.name, e.name` starting at
+/// `idx`.
+fn enum_ref(row: &Row<'_>, idx: usize) -> rusqlite::Result<EnumRef> {
+    Ok(EnumRef {
+        id: EnumId(row.get(idx)?),
+        interface_id: InterfaceId(row.get(idx + 1)?),
+        repo: row.get(idx + 2)?,
+        protocol: row.get(idx + 3)?,
+        interface: row.get(idx + 4)?,
+        enum_: row.get(idx + 5)?,
+    })
+}
+
+/// Reads `p.protocol_id, r.name, p.name` starting at `idx`.
+fn protocol_ref(row: &Row<'_>, idx: usize) -> rusqlite::Result<ProtocolRef> {
+    Ok(ProtocolRef {
+        id: ProtocolId(row.get(idx)?),
+        repo: row.get(idx + 1)?,
+        protocol: row.get(idx + 2)?,
+    })
+}
+
+impl Database {
+    /// Opens the database at `path` in read-only mode.
+    ///
+    /// Fails if the database uses a version older than [`MIN_SCHEMA_VERSION`].
+    /// Newer versions are accepted since schema changes usually only add tables
+    /// and columns. If a newer schema removed something that a query uses, the
+    /// query fails with [`DbError::Query`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DbError> {
+        let path = path.as_ref();
+        let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| DbError::Open(path.to_path_buf(), e))?;
+        let version: i64 = db
+            .query_row("pragma user_version", [], |r| r.get(0))
+            .map_err(DbError::ReadSchemaVersion)?;
+        if version < MIN_SCHEMA_VERSION {
+            return Err(DbError::SchemaVersion(version));
+        }
+        Ok(Self { db })
+    }
+
+    fn query<T>(
+        &self,
+        table: &'static str,
+        sql: &str,
+        params: &[&dyn ToSql],
+        mut f: impl FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    ) -> Result<Vec<T>, DbError> {
+        self.db
+            .prepare_cached(sql)
+            .and_then(|mut s| {
+                s.query_map(params, |r| f(r))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|e| DbError::Query(table, e))
+    }
+
+    /// Returns all interfaces with the given name, ordered by repo and protocol.
+    pub fn find_interfaces(&self, name: &str) -> Result<Vec<InterfaceRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select i.interface_id, i.protocol_id, r.name, p.name, i.name
+            from interface i
+            join protocol p using (protocol_id)
+            join repo r using (repo_id)
+            where i.name = ?1
+            order by r.name, p.name
+        ";
+        self.query("interface", sql, &[&name], |r| interface_ref(r, 0))
+    }
+
+    /// Returns the interface with the given name.
+    ///
+    /// If several repos contain such an interface, the one from the repo that
+    /// comes first in `repos` is returned. If none of them is listed in `repos`,
+    /// the first one in the order of [`find_interfaces`](Self::find_interfaces)
+    /// is returned.
+    pub fn find_interface(
+        &self,
+        name: &str,
+        repos: &[&str],
+    ) -> Result<Option<InterfaceRef>, DbError> {
+        let interfaces = self.find_interfaces(name)?;
+        let rank = |i: &InterfaceRef| {
+            repos
+                .iter()
+                .position(|r| *r == i.repo)
+                .unwrap_or(repos.len())
+        };
+        Ok(interfaces.into_iter().min_by_key(rank))
+    }
+
+    /// Returns all protocols with the given name, ordered by repo.
+    pub fn find_protocols(&self, name: &str) -> Result<Vec<ProtocolRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select p.protocol_id, r.name, p.name
+            from protocol p
+            join repo r using (repo_id)
+            where p.name = ?1
+            order by r.name
+        ";
+        self.query("protocol", sql, &[&name], |r| protocol_ref(r, 0))
+    }
+
+    /// Returns the protocol with the given name in the given repo.
+    pub fn find_protocol(&self, repo: &str, name: &str) -> Result<Option<ProtocolRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select p.protocol_id, r.name, p.name
+            from protocol p
+            join repo r using (repo_id)
+            where r.name = ?1 and p.name = ?2
+        ";
+        let rows = self.query("protocol", sql, &[&repo, &name], |r| protocol_ref(r, 0))?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// Loads an interface including its messages and enums.
+    pub fn interface(&self, id: InterfaceId) -> Result<Option<Interface>, DbError> {
+        let interfaces = self.load_interfaces("i.interface_id = ?1", &[&id.0])?;
+        Ok(interfaces.into_iter().next().map(|(_, i)| i))
+    }
+
+    /// Loads a protocol including its interfaces.
+    pub fn protocol(&self, id: ProtocolId) -> Result<Option<Protocol>, DbError> {
+        let mut protocols = self.load_protocols("p.protocol_id = ?1", &[&id.0])?;
+        let mut interfaces = group(self.load_interfaces("i.protocol_id = ?1", &[&id.0])?);
+        Ok(protocols.pop().map(|(_, _, mut p)| {
+            p.interfaces = interfaces.remove(&id.0).unwrap_or_default();
+            p
+        }))
+    }
+
+    /// Returns the interfaces that the `interface` attribute of an arg resolves to.
+    ///
+    /// `message` must be a message of the interface and `arg` is the index of the
+    /// arg in `message.args`.
+    pub fn arg_interfaces(
+        &self,
+        interface: InterfaceId,
+        message: &Message,
+        arg: usize,
+    ) -> Result<Vec<InterfaceRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select i2.interface_id, i2.protocol_id, r2.name, p2.name, i2.name
+            from message m
+            join arg a using (message_id)
+            join rel_arg_interface rai using (arg_id)
+            join interface i2 on i2.interface_id = rai.interface_id
+            join protocol p2 on p2.protocol_id = i2.protocol_id
+            join repo r2 on r2.repo_id = p2.repo_id
+            where m.interface_id = ?1 and m.is_request = ?2 and m.number = ?3
+                and a.position = ?4
+            order by r2.name, p2.name, i2.name
+        ";
+        let params: &[&dyn ToSql] = &[
+            &interface.0,
+            &message.is_request,
+            &(message.message_id as i64),
+            &(arg as i64),
+        ];
+        self.query("rel_arg_interface", sql, params, |r| interface_ref(r, 0))
+    }
+
+    /// Returns the objects that can be created by the messages of an interface.
+    pub fn children(&self, interface: InterfaceId) -> Result<Vec<ObjectFactory>, DbError> {
+        self.object_factories("f.parent_interface_id = ?1", &[&interface.0])
+    }
+
+    /// Returns the messages that create objects of an interface.
+    pub fn creators(&self, interface: InterfaceId) -> Result<Vec<ObjectFactory>, DbError> {
+        self.object_factories("f.child_interface_id = ?1", &[&interface.0])
+    }
+
+    /// Returns all interfaces that are advertised by `wl_registry`, ordered by
+    /// repo, protocol, and name.
+    ///
+    /// These are the interfaces that are not created by any `new_id` arg with an
+    /// `interface` attribute, except for the overrides in the generator.
+    pub fn globals(&self) -> Result<Vec<InterfaceRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select i.interface_id, i.protocol_id, r.name, p.name, i.name
+            from interface i
+            join protocol p using (protocol_id)
+            join repo r using (repo_id)
+            where i.is_global
+            order by r.name, p.name, i.name
+        ";
+        self.query("interface", sql, &[], |r| interface_ref(r, 0))
+    }
+
+    /// Loads the object factories matching `filter`.
+    ///
+    /// The filter can refer to the object_factory table as `f`.
+    fn object_factories(
+        &self,
+        filter: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Vec<ObjectFactory>, DbError> {
+        // language=sqlite
+        let sql = format!(
+            "select
+                 i.interface_id, i.protocol_id, r.name, p.name, i.name,
+                 m.is_request, m.name, a.name,
+                 i2.interface_id, i2.protocol_id, r2.name, p2.name, i2.name,
+                 f.since
+             from object_factory f
+             join arg a using (arg_id)
+             join message m on m.message_id = f.message_id
+             join interface i on i.interface_id = f.parent_interface_id
+             join protocol p using (protocol_id)
+             join repo r using (repo_id)
+             join interface i2 on i2.interface_id = f.child_interface_id
+             join protocol p2 on p2.protocol_id = i2.protocol_id
+             join repo r2 on r2.repo_id = p2.repo_id
+             where {filter}
+             order by r.name, p.name, i.name, m.is_request desc, m.number, a.position,
+                      r2.name, p2.name, i2.name"
+        );
+        self.query("object_factory", &sql, params, |r| {
+            Ok(ObjectFactory {
+                parent: interface_ref(r, 0)?,
+                is_request: r.get(5)?,
+                message: r.get(6)?,
+                arg: r.get(7)?,
+                child: interface_ref(r, 8)?,
+                since: r.get(13)?,
+            })
+        })
+    }
+
+    /// Returns the enums that the `enum` attribute of an arg resolves to.
+    ///
+    /// The arguments are the same as for [`arg_interfaces`](Self::arg_interfaces).
+    pub fn arg_enums(
+        &self,
+        interface: InterfaceId,
+        message: &Message,
+        arg: usize,
+    ) -> Result<Vec<EnumRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            select e.enum_id, e.interface_id, r2.name, p2.name, i2.name, e.name
+            from message m
+            join arg a using (message_id)
+            join rel_arg_enum rae using (arg_id)
+            join enum e on e.enum_id = rae.enum_id
+            join interface i2 on i2.interface_id = e.interface_id
+            join protocol p2 on p2.protocol_id = i2.protocol_id
+            join repo r2 on r2.repo_id = p2.repo_id
+            where m.interface_id = ?1 and m.is_request = ?2 and m.number = ?3
+                and a.position = ?4
+            order by r2.name, p2.name, i2.name, e.name
+        ";
+        let params: &[&dyn ToSql] = &[
+            &interface.0,
+            &message.is_request,
+            &(message.message_id as i64),
+            &(arg as i64),
+        ];
+        self.query("rel_arg_enum", sql, params, |r| enum_ref(r, 0))
+    }
+
+    /// Returns the other protocols whose interfaces or enums are referenced by
+    /// the args of a protocol.
+    pub fn dependencies(&self, protocol: ProtocolId) -> Result<Vec<ProtocolRef>, DbError> {
+        // language=sqlite
+        let sql = "
+            with target(interface_id) as (
+                select rai.interface_id
+                from rel_arg_interface rai
+                join arg a using (arg_id)
+                join message m using (message_id)
+                join interface i on i.interface_id = m.interface_id
+                where i.protocol_id = ?1
+                union
+                select e.interface_id
+                from rel_arg_enum rae
+                join enum e using (enum_id)
+                join arg a on a.arg_id = rae.arg_id
+                join message m using (message_id)
+                join interface i on i.interface_id = m.interface_id
+                where i.protocol_id = ?1
+            )
+            select distinct p.protocol_id, r.name, p.name
+            from target t
+            join interface i using (interface_id)
+            join protocol p using (protocol_id)
+            join repo r using (repo_id)
+            where p.protocol_id != ?1
+            order by r.name, p.name
+        ";
+        self.query("protocol", sql, &[&protocol.0], |r| protocol_ref(r, 0))
+    }
+
+    /// Returns the transitive [`dependencies`](Self::dependencies) of a
+    /// protocol in breadth-first order, excluding the protocol itself.
+    pub fn walk_dependencies(&self, protocol: ProtocolId) -> Result<Vec<ProtocolRef>, DbError> {
+        let mut res = vec![];
+        let mut seen = HashSet::from([protocol]);
+        let mut queue = VecDeque::from([protocol]);
+        while let Some(protocol) = queue.pop_front() {
+            for dep in self.dependencies(protocol)? {
+                if seen.insert(dep.id) {
+                    queue.push_back(dep.id);
+                    res.push(dep);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Loads the interfaces matching `filter` and returns them with the IDs of
+    /// their protocols.
+    ///
+    /// The filter can refer to the interface table as `i`.
+    fn load_interfaces(
+        &self,
+        filter: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Vec<(i64, Interface)>, DbError> {
+        // language=sqlite
+        let sql = format!(
+            "select
+                 a.message_id, a.name, t.name, a.summary, a.interface_name, a.allow_null,
+                 a.enum_name, d.summary, d.body
+             from arg a
+             join type t using (type_id)
+             join message m using (message_id)
+             join interface i on i.interface_id = m.interface_id
+             left join description d on d.description_id = a.description_id
+             where {filter}
+             order by a.message_id, a.position"
+        );
+        let args = self.query("arg", &sql, params, |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, String>(2)?,
+                Arg {
+                    name: r.get(1)?,
+                    ty: ArgType::Int,
+                    summary: r.get(3)?,
+                    description: description(r, 7)?,
+                    interface: r.get(4)?,
+                    allow_null: r.get(5)?,
+                    enum_: r.get(6)?,
+                },
+            ))
+        })?;
+        let mut typed_args = Vec::with_capacity(args.len());
+        for (message_id, ty, mut arg) in args {
+            arg.ty = ArgType::from_name(&ty).ok_or(DbError::UnknownArgType(ty))?;
+            typed_args.push((message_id, arg));
+        }
+        let mut args = group(typed_args);
+        // language=sqlite
+        let sql = format!(
+            "select
+                 m.message_id, m.interface_id, m.name, m.number, m.is_request, m.is_destructor,
+                 m.since, m.deprecated_since, d.summary, d.body
+             from message m
+             join interface i using (interface_id)
+             left join description d on d.description_id = m.description_id
+             where {filter}
+             order by m.interface_id, m.is_request desc, m.number"
+        );
+        let messages = self.query("message", &sql, params, |r| {
+            Ok((
+                r.get::<_, i64>(1)?,
+                Message {
+                    name: r.get(2)?,
+                    message_id: r.get::<_, u32>(3)? as usize,
+                    is_request: r.get(4)?,
+                    ty: r.get::<_, bool>(5)?.then_some(MessageType::Destructor),
+                    since: r.get(6)?,
+                    deprecated_since: r.get(7)?,
+                    description: description(r, 8)?,
+                    args: args.remove(&r.get(0)?).unwrap_or_default(),
+                },
+            ))
+        })?;
+        let mut messages = group(messages);
+        // language=sqlite
+        let sql = format!(
+            "select
+                 n.enum_id, n.name, n.value_str, n.value, n.summary, n.since, n.deprecated_since,
+                 d.summary, d.body
+             from entry n
+             join enum e using (enum_id)
+             join interface i on i.interface_id = e.interface_id
+             left join description d on d.description_id = n.description_id
+             where {filter}
+             order by n.entry_id"
+        );
+        let entries = self.query("entry", &sql, params, |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                Entry {
+                    name: r.get(1)?,
+                    value: r.get(2)?,
+                    value_i64: r.get(3)?,
+                    summary: r.get(4)?,
+                    since: r.get(5)?,
+                    deprecated_since: r.get(6)?,
+                    description: description(r, 7)?,
+                },
+            ))
+        })?;
+        let mut entries = group(entries);
+        // language=sqlite
+        let sql = format!(
+            "select e.enum_id, e.interface_id, e.name, e.since, e.is_bitfield, d.summary, d.body
+             from enum e
+             join interface i using (interface_id)
+             left join description d on d.description_id = e.description_id
+             where {filter}
+             order by e.enum_id"
+        );
+        let enums = self.query("enum", &sql, params, |r| {
+            Ok((
+                r.get::<_, i64>(1)?,
+                Enum {
+                    name: r.get(2)?,
+                    since: r.get(3)?,
+                    bitfield: r.get(4)?,
+                    description: description(r, 5)?,
+                    entries: entries.remove(&r.get(0)?).unwrap_or_default(),
+                },
+            ))
+        })?;
+        let mut enums = group(enums);
+        // language=sqlite
+        let sql = format!(
+            "select i.interface_id, i.protocol_id, i.name, i.version, d.summary, d.body
+             from interface i
+             left join description d using (description_id)
+             where {filter}
+             order by i.interface_id"
+        );
+        self.query("interface", &sql, params, |r| {
+            Ok((
+                r.get::<_, i64>(1)?,
+                Interface {
+                    name: r.get(2)?,
+                    version: r.get(3)?,
+                    description: description(r, 4)?,
+                    messages: messages.remove(&r.get(0)?).unwrap_or_default(),
+                    enums: enums.remove(&r.get(0)?).unwrap_or_default(),
+                    elements: vec![],
+                },
+            ))
+        })
+    }
+
+    /// Loads the protocols matching `filter` without their interfaces and
+    /// returns them with their IDs and the IDs of their repos.
+    ///
+    /// The filter can refer to the protocol table as `p`.
+    fn load_protocols(
+        &self,
+        filter: &str,
+        params: &[&dyn ToSql],
+    ) -> Result<Vec<(i64, i64, Protocol)>, DbError> {
+        // language=sqlite
+        let sql = format!(
+            "select p.protocol_id, p.repo_id, p.name, p.path, p.copyright, d.summary, d.body
+             from protocol p
+             left join description d using (description_id)
+             where {filter}
+             order by p.name"
+        );
+        self.query("protocol", &sql, params, |r| {
+            Ok((
+                r.get(0)?,
+                r.get(1)?,
+                Protocol {
+                    name: r.get(2)?,
+                    path: r.get(3)?,
+                    copyright: r
+                        .get::<_, Option<String>>(4)?
+                        .map(|body| Copyright { body }),
+                    description: description(r, 5)?,
+                    interfaces: vec![],
+                },
+            ))
+        })
+    }
+
+    /// Loads the whole database.
+    pub fn load(&self) -> Result<Model, DbError> {
+        let mut interfaces = group(self.load_interfaces("true", &[])?);
+        let mut protocols = HashMap::<_, Vec<_>>::new();
+        for (protocol_id, repo_id, mut protocol) in self.load_protocols("true", &[])? {
+            protocol.interfaces = interfaces.remove(&protocol_id).unwrap_or_default();
+            protocols.entry(repo_id).or_default().push(protocol);
+        }
+        // language=sqlite
+        let repos = self.query(
+            "repo",
+            "select repo_id, name, url from repo order by name",
+            &[],
+            |r| {
+                Ok(Repo {
+                    name: r.get(1)?,
+                    url: r.get(2)?,
+                    protocols: protocols.remove(&r.get(0)?).unwrap_or_default(),
+                })
+            },
+        )?;
+
+        let read_arg_path = |r: &Row<'_>| -> rusqlite::Result<ArgPath> {
+            Ok(ArgPath {
+                repo: r.get(0)?,
+                protocol: r.get(1)?,
+                interface: r.get(2)?,
+                is_request: r.get(3)?,
+                message: r.get(4)?,
+                arg: r.get(5)?,
+            })
+        };
+        // language=sqlite
+        let sql = "
+            select
+                r.name, p.name, i.name, m.is_request, m.name, a.name,
+                i2.interface_id, i2.protocol_id, r2.name, p2.name, i2.name
+            from rel_arg_interface rai
+            join arg a using (arg_id)
+            join message m using (message_id)
+            join interface i on i.interface_id = m.interface_id
+            join protocol p using (protocol_id)
+            join repo r using (repo_id)
+            join interface i2 on i2.interface_id = rai.interface_id
+            join protocol p2 on p2.protocol_id = i2.protocol_id
+            join repo r2 on r2.repo_id = p2.repo_id
+            order by r2.name, p2.name, i2.name
+        ";
+        let rows = self.query("rel_arg_interface", sql, &[], |r| {
+            Ok((read_arg_path(r)?, interface_ref(r, 6)?))
+        })?;
+        let mut arg_interfaces = HashMap::<_, Vec<_>>::new();
+        for (path, target) in rows {
+            arg_interfaces.entry(path).or_default().push(target);
+        }
+        // language=sqlite
+        let sql = "
+            select
+                r.name, p.name, i.name, m.is_request, m.name, a.name,
+                e.enum_id, e.interface_id, r2.name, p2.name, i2.name, e.name
+            from rel_arg_enum rae
+            join arg a using (arg_id)
+            join message m using (message_id)
+            join interface i on i.interface_id = m.interface_id
+            join protocol p using (protocol_id)
+            join repo r using (repo_id)
+            join enum e on e.enum_id = rae.enum_id
+            join interface i2 on i2.interface_id = e.interface_id
+            join protocol p2 on p2.protocol_id = i2.protocol_id
+            join repo r2 on r2.repo_id = p2.repo_id
+            order by r2.name, p2.name, i2.name, e.name
+        ";
+        let rows = self.query("rel_arg_enum", sql, &[], |r| {
+            Ok((read_arg_path(r)?, enum_ref(r, 6)?))
+        })?;
+        let mut arg_enums = HashMap::<_, Vec<_>>::new();
+        for (path, target) in rows {
+            arg_enums.entry(path).or_default().push(target);
+        }
+
+        Ok(Model {
+            repos,
+            arg_interfaces,
+            arg_enums,
+        })
+    }
+}
@@ -0,0 +1,830 @@
+use {
+    crate::objects::{
+        Index, Objects, Resolved, bind_warning, entry_names, entry_warnings, message_warnings,
+    },
+    clap::{Args, ValueEnum},
+    linearize::{Linearize, StaticMap, static_map},
+    serde::Serialize,
+    serde_json::Value,
+    std::{
+        collections::VecDeque,
+        fmt::Write as _,
+        fs,
+        io::{self, Read, Write},
+        mem,
+        path::PathBuf,
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, ArgType, Message, MessageType},
+        db::{Database, DbError},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("could not read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("could not read from stdin")]
+    ReadStdin(#[source] io::Error),
+    #[error("the capture record at offset {0} has the invalid sender {1}")]
+    InvalidSender(usize, u8),
+    #[error("the capture record at offset {0} is truncated")]
+    TruncatedRecord(usize),
+    #[error("line {} of {} is not of the form `client|server FD`", .1, .0.display())]
+    InvalidFd(PathBuf, usize),
+    #[error("could not write to stdout")]
+    WriteStdout(#[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct DecodeArgs {
+    /// The database to read the protocols from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The output format.
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// The input contains only the bytes sent by this side instead of capture
+    /// records.
+    #[clap(long, value_enum)]
+    raw: Option<Sender>,
+    /// A file with one line `client FD` or `server FD` per file descriptor in
+    /// the order in which they were sent.
+    #[clap(long)]
+    fds: Option<PathBuf>,
+    /// The repos whose interfaces are used if several repos contain an
+    /// interface with the same name.
+    #[clap(long, default_values = ["wayland", "wayland-protocols"])]
+    prefer: Vec<String>,
+    /// The capture file (default: stdin).
+    input: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Format {
+    /// One line per message.
+    Text,
+    /// One JSON object per message.
+    Json,
+}
+
+/// The side of the connection that sent some bytes.
+#[derive(ValueEnum, Linearize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Sender {
+    /// The client. The bytes contain requests.
+    Client,
+    /// The server. The bytes contain events.
+    Server,
+}
+
+pub fn main(args: DecodeArgs) -> Result<(), DecodeError> {
+    let mut input = vec![];
+    match &args.input {
+        Some(path) => {
+            input = fs::read(path).map_err(|e| DecodeError::Read(path.clone(), e))?;
+        }
+        None => {
+            io::stdin()
+                .lock()
+                .read_to_end(&mut input)
+                .map_err(DecodeError::ReadStdin)?;
+        }
+    }
+    let records = match args.raw {
+        Some(sender) => vec![(sender, &input[..])],
+        None => records(&input)?,
+    };
+    let model = Database::open(&args.db)?.load()?;
+    let index = Index::new(&model, &args.prefer);
+    let mut decoder = Decoder::new(&index);
+    if let Some(path) = &args.fds {
+        let fds = fs::read_to_string(path).map_err(|e| DecodeError::Read(path.clone(), e))?;
+        for (idx, line) in fds.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fd = line.split_once(' ').and_then(|(sender, fd)| {
+                let sender = Sender::from_str(sender, false).ok()?;
+                Some((sender, fd.trim().parse().ok()?))
+            });
+            let Some((sender, fd)) = fd else {
+                return Err(DecodeError::InvalidFd(path.clone(), idx + 1));
+            };
+            decoder.streams[sender].fds.push_back(fd);
+        }
+    }
+    let mut stdout = io::stdout().lock();
+    let mut write = |messages: Vec<Decoded>| -> Result<(), DecodeError> {
+        for message in messages {
+            let line = match args.format {
+                Format::Text => message.text(),
+                Format::Json => serde_json::to_string(&message).unwrap(),
+            };
+            writeln!(stdout, "{line}").map_err(DecodeError::WriteStdout)?;
+        }
+        Ok(())
+    };
+    for (sender, bytes) in records {
+        write(decoder.feed(sender, bytes))?;
+    }
+    write(decoder.finish())
+}
+
+/// Splits a capture into its records.
+///
+/// Every record consists of a byte that identifies the sender (0 for the
+/// client, 1 for the server), the length of the data as a little-endian u32,
+/// and the data.
+fn records(input: &[u8]) -> Result<Vec<(Sender, &[u8])>, DecodeError> {
+    let mut res = vec![];
+    let mut pos = 0;
+    while pos < input.len() {
+        let sender = match input[pos] {
+            0 => Sender::Client,
+            1 => Sender::Server,
+            n => return Err(DecodeError::InvalidSender(pos, n)),
+        };
+        let Some(len) = input.get(pos + 1..pos + 5) else {
+            return Err(DecodeError::TruncatedRecord(pos));
+        };
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        let Some(data) = input.get(pos + 5..pos + 5 + len) else {
+            return Err(DecodeError::TruncatedRecord(pos));
+        };
+        res.push((sender, data));
+        pos += 5 + len;
+    }
+    Ok(res)
+}
+
+/// The bytes sent by one side that have not been decoded yet.
+#[derive(Default)]
+struct Stream {
+    buf: Vec<u8>,
+    /// The offset of the start of `buf` in the stream.
+    offset: usize,
+    fds: VecDeque<i32>,
+    /// The stream contained an invalid message size. The rest of it is skipped
+    /// since the start of the next message is unknown.
+    broken: bool,
+}
+
+#[derive(Serialize)]
+struct Decoded {
+    sender: Sender,
+    /// The offset of the message in the bytes sent by the sender.
+    offset: usize,
+    object_id: u32,
+    opcode: u16,
+    size: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    args: Vec<DecodedArg>,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DecodedArg {
+    name: String,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    value: Value,
+    /// The interface of an object or new_id arg.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+    /// The names of the enum entries of an int or uint arg.
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    enum_: Option<String>,
+    #[serde(skip)]
+    text: String,
+}
+
+impl Decoded {
+    fn text(&self) -> String {
+        let arrow = match self.sender {
+            Sender::Client => "->",
+            Sender::Server => "<-",
+        };
+        let mut res = format!("[{:8}] {arrow} ", self.offset);
+        if let (Some(repo), Some(protocol)) = (&self.repo, &self.protocol) {
+            let _ = write!(res, "{repo}/{protocol}: ");
+        }
+        if let Some(interface) = &self.interface {
+            res.push_str(interface);
+        }
+        let _ = write!(res, "#{}", self.object_id);
+        if let Some(version) = self.version {
+            let _ = write!(res, " v{version}");
+        }
+        match &self.message {
+            Some(message) => {
+                let kind = match self.sender {
+                    Sender::Client => "request",
+                    Sender::Server => "event",
+                };
+                let args: Vec<_> = self
+                    .args
+                    .iter()
+                    .map(|a| format!("{}={}", a.name, a.text))
+                    .collect();
+                let _ = write!(res, " {kind} {message}({})", args.join(", "));
+            }
+            None => {
+                let _ = write!(res, " opcode {} ({} bytes)", self.opcode, self.size);
+            }
+        }
+        for warning in &self.warnings {
+            let _ = write!(res, "\n    warning: {warning}");
+        }
+        res
+    }
+}
+
+/// A value as it is encoded on the wire.
+enum WireValue {
+    Int(i32),
+    Uint(u32),
+    Fixed(i32),
+    String(Option<Vec<u8>>),
+    Object(u32),
+    Array(Vec<u8>),
+    Fd(Option<i32>),
+}
+
+struct Decoder<'a> {
+    index: &'a Index<'a>,
+    objects: Objects<'a>,
+    streams: StaticMap<Sender, Stream>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(index: &'a Index<'a>) -> Self {
+        Self {
+            index,
+            objects: Objects::new(index),
+            streams: static_map!(_ => Stream::default()),
+        }
+    }
+
+    /// Adds bytes sent by one side and returns the messages that are complete.
+    fn feed(&mut self, sender: Sender, bytes: &[u8]) -> Vec<Decoded> {
+        let mut res = vec![];
+        let stream = &mut self.streams[sender];
+        if stream.broken {
+            return res;
+        }
+        // Decoding borrows the decoder, so the messages are read from a local
+        // buffer. The decoded bytes are removed once at the end.
+        let mut buf = mem::take(&mut stream.buf);
+        buf.extend_from_slice(bytes);
+        let mut pos = 0;
+        loop {
+            let stream = &mut self.streams[sender];
+            let rest = &buf[pos..];
+            if rest.len() < 8 {
+                break;
+            }
+            let object_id = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+            let word = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+            let size = (word >> 16) as usize;
+            if size < 8 || !size.is_multiple_of(4) {
+                res.push(Decoded {
+                    sender,
+                    offset: stream.offset,
+                    object_id,
+                    opcode: word as u16,
+                    size: size as u16,
+                    interface: None,
+                    version: None,
+                    repo: None,
+                    protocol: None,
+                    message: None,
+                    args: vec![],
+                    warnings: vec![format!(
+                        "invalid message size {size}, skipping the rest of the stream"
+                    )],
+                });
+                stream.broken = true;
+                pos = buf.len();
+                break;
+            }
+            if rest.len() < size {
+                break;
+            }
+            let offset = stream.offset;
+            stream.offset += size;
+            res.push(self.decode(sender, offset, &rest[..size]));
+            pos += size;
+        }
+        buf.drain(..pos);
+        self.streams[sender].buf = buf;
+        res
+    }
+
+    /// Returns warnings for the bytes that do not form complete messages.
+    fn finish(&mut self) -> Vec<Decoded> {
+        let mut res = vec![];
+        for (sender, stream) in self.streams.iter() {
+            if stream.buf.is_empty() {
+                continue;
+            }
+            let mut header = [0; 8];
+            let len = stream.buf.len().min(8);
+            header[..len].copy_from_slice(&stream.buf[..len]);
+            let word = u32::from_le_bytes(header[4..8].try_into().unwrap());
+            res.push(Decoded {
+                sender,
+                offset: stream.offset,
+                object_id: u32::from_le_bytes(header[0..4].try_into().unwrap()),
+                opcode: word as u16,
+                size: (word >> 16) as u16,
+                interface: None,
+                version: None,
+                repo: None,
+                protocol: None,
+                message: None,
+                args: vec![],
+                warnings: vec![format!(
+                    "the stream ends with an incomplete message of {} bytes",
+                    stream.buf.len(),
+                )],
+            });
+        }
+        res
+    }
+
+    /// Decodes a complete message.
+    fn decode(&mut self, sender: Sender, offset: usize, bytes: &[u8]) -> Decoded {
+        let object_id = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let word = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        let mut res = Decoded {
+            sender,
+            offset,
+            object_id,
+            opcode: word as u16,
+            size: (word >> 16) as u16,
+            interface: None,
+            version: None,
+            repo: None,
+            protocol: None,
+            message: None,
+            args: vec![],
+            warnings: vec![],
+        };
+        let Some(object) = self.objects.get(object_id) else {
+            let warning = match self.objects.is_deleted(object_id) {
+                true => {
+                    format!("#{object_id} is used after its ID was deleted, skipping the message")
+                }
+                false => format!("unknown object #{object_id}, skipping the message"),
+            };
+            res.warnings.push(warning);
+            return res;
+        };
+        res.interface = Some(object.name.clone());
+        res.version = object.version;
+        // The server can send events before it receives a destructor request. The
+        // client discards them.
+        if let Some(destructor) = object.destroyed
+            && (sender == Sender::Client || !destructor.is_request)
+        {
+            res.warnings.push(format!(
+                "{}#{object_id} is used after it was destroyed",
+                object.name
+            ));
+        }
+        let Some(interface) = object.interface else {
+            res.warnings
+                .push(format!("unknown interface {}", object.name));
+            return res;
+        };
+        let name = &interface.interface.name;
+        let Some(message) = interface.message_by_opcode(res.opcode, sender == Sender::Client)
+        else {
+            res.warnings
+                .push(format!("{name} has no message with opcode {}", res.opcode));
+            return res;
+        };
+        res.repo = Some(interface.repo.name.clone());
+        res.protocol = Some(interface.protocol.name.clone());
+        res.message = Some(message.name.clone());
+
+        let wire = message.wire_args();
+        let mut values = vec![];
+        let mut body = Body {
+            bytes: &bytes[8..],
+            pos: 0,
+        };
+        for arg in &wire {
+            let value = match arg.ty {
+                ArgType::Int => body.u32().map(|v| WireValue::Int(v as i32)),
+                ArgType::Uint => body.u32().map(WireValue::Uint),
+                ArgType::Fixed => body.u32().map(|v| WireValue::Fixed(v as i32)),
+                ArgType::String => body.array().map(|s| {
+                    WireValue::String(s.map(|s| s.strip_suffix(&[0]).unwrap_or(s).to_vec()))
+                }),
+                ArgType::Object | ArgType::NewId => body.u32().map(WireValue::Object),
+                ArgType::Array => body
+                    .array()
+                    .map(|a| WireValue::Array(a.unwrap_or_default().to_vec())),
+                ArgType::Fd => Some(WireValue::Fd(self.streams[sender].fds.pop_front())),
+            };
+            let Some(value) = value else {
+                res.warnings.push("the message is truncated".to_string());
+                return res;
+            };
+            values.push(value);
+        }
+        if body.pos > body.bytes.len() {
+            res.warnings.push("the message is truncated".to_string());
+            return res;
+        }
+        if body.pos < body.bytes.len() {
+            res.warnings.push(format!(
+                "the message has {} unused bytes",
+                body.bytes.len() - body.pos
+            ));
+        }
+
+        let mut pos = 0;
+        let mut created = vec![];
+        for (idx, arg) in message.args.iter().enumerate() {
+            let n = wire[pos..].iter().take_while(|w| w.arg == idx).count();
+            let values = &values[pos..pos + n];
+            pos += n;
+            let mut decoded = DecodedArg {
+                name: arg.name.clone(),
+                ty: arg.ty.name(),
+                value: Value::Null,
+                interface: None,
+                enum_: None,
+                text: String::new(),
+            };
+            match (&values[n - 1], arg.ty) {
+                (WireValue::Int(v), _) => {
+                    decoded.value = (*v).into();
+                    decoded.text = v.to_string();
+                    self.enum_arg(&mut res, &mut decoded, interface, message, arg, *v as i64);
+                }
+                (WireValue::Uint(v), _) => {
+                    decoded.value = (*v).into();
+                    decoded.text = v.to_string();
+                    self.enum_arg(&mut res, &mut decoded, interface, message, arg, *v as i64);
+                }
+                (WireValue::Fixed(v), _) => {
+                    let v = *v as f64 / 256.0;
+                    decoded.value = v.into();
+                    decoded.text = v.to_string();
+                }
+                (WireValue::String(s), _) => match s {
+                    Some(s) => {
+                        let s = String::from_utf8_lossy(s);
+                        decoded.text = format!("{s:?}");
+                        decoded.value = s.into();
+                    }
+                    None => self.null_arg(&mut res, &mut decoded, arg),
+                },
+                (WireValue::Object(0), _) => self.null_arg(&mut res, &mut decoded, arg),
+                (&WireValue::Object(id), ArgType::NewId) => {
+                    let (child, version) = match (&arg.interface, values) {
+                        (Some(child), _) => (child.clone(), res.version),
+                        (None, [WireValue::String(Some(child)), WireValue::Uint(version), _]) => {
+                            let child = String::from_utf8_lossy(child).into_owned();
+                            if let Some(warning) = bind_warning(self.index, &child, *version) {
+                                res.warnings.push(warning);
+                            }
+                            (child, Some(*version))
+                        }
+                        _ => ("[unknown]".to_string(), None),
+                    };
+                    if self.index.find(&child).is_none() {
+                        res.warnings.push(format!("unknown interface {child}"));
+                    }
+                    decoded.value = id.into();
+                    decoded.text = format!("new {child}#{id}");
+                    if let Some(version) = version {
+                        let _ = write!(decoded.text, " v{version}");
+                    }
+                    decoded.interface = Some(child.clone());
+                    created.push((id, child, version));
+                }
+                (&WireValue::Object(id), _) => {
+                    let name = match self.objects.get(id) {
+                        Some(object) => object.name.clone(),
+                        None => {
+                            res.warnings.push(format!("unknown object #{id}"));
+                            "[unknown]".to_string()
+                        }
+                    };
+                    if let Some(expected) = &arg.interface
+                        && self.objects.get(id).is_some()
+                        && *expected != name
+                    {
+                        res.warnings.push(format!(
+                            "{} must be a {expected} but #{id} is a {name}",
+                            arg.name
+                        ));
+                    }
+                    decoded.value = id.into();
+                    decoded.text = format!("{name}#{id}");
+                    decoded.interface = Some(name);
+                }
+                (WireValue::Array(a), _) => {
+                    let mut hex = String::new();
+                    for b in a {
+                        let _ = write!(hex, "{b:02x}");
+                    }
+                    decoded.value = hex.into();
+                    decoded.text = format!("array[{}]", a.len());
+                }
+                (WireValue::Fd(fd), _) => match fd {
+                    Some(fd) => {
+                        decoded.value = (*fd).into();
+                        decoded.text = format!("fd {fd}");
+                    }
+                    None => {
+                        res.warnings
+                            .push(format!("no file descriptor annotated for {}", arg.name));
+                        decoded.text = "fd".to_string();
+                    }
+                },
+            }
+            res.args.push(decoded);
+        }
+
+        if let Some(version) = res.version {
+            res.warnings
+                .extend(message_warnings(name, object_id, message, version));
+        }
+        if name == "wl_registry"
+            && message.name == "global"
+            && sender == Sender::Server
+            && let Some(WireValue::String(Some(global))) = values.get(1)
+        {
+            let global = String::from_utf8_lossy(global);
+            if self.index.find(&global).is_none() {
+                res.warnings.push(format!("unknown interface {global}"));
+            }
+        }
+        for (id, child, version) in created {
+            if let Some(warning) = self.objects.create(id, &child, version) {
+                res.warnings.push(warning);
+            }
+        }
+        if message.ty == Some(MessageType::Destructor) {
+            self.objects.destroy(object_id, message);
+        }
+        if name == "wl_display"
+            && message.name == "delete_id"
+            && sender == Sender::Server
+            && let Some(WireValue::Uint(deleted)) = values.first()
+            && self.objects.delete(*deleted).is_none()
+        {
+            res.warnings
+                .push(format!("delete_id for unknown object #{deleted}"));
+        }
+        res
+    }
+
+    fn null_arg(&self, res: &mut Decoded, decoded: &mut DecodedArg, arg: &Arg) {
+        if !arg.allow_null {
+            res.warnings
+                .push(format!("{} is null but not nullable", arg.name));
+        }
+        decoded.text = "nil".to_string();
+    }
+
+    /// Adds the names of the enum entries of an int or uint arg.
+    fn enum_arg(
+        &self,
+        res: &mut Decoded,
+        decoded: &mut DecodedArg,
+        interface: Resolved<'a>,
+        message: &Message,
+        arg: &Arg,
+        value: i64,
+    ) {
+        let Some(enum_) = self.index.arg_enum(interface, message, arg) else {
+            return;
+        };
+        let names = entry_names(enum_, value);
+        let _ = write!(decoded.text, " ({names})");
+        decoded.enum_ = Some(names);
+        if let Some(version) = res.version {
+            res.warnings.extend(entry_warnings(enum_, value, version));
+        }
+    }
+}
+
+/// The body of a message after the header.
+struct Body<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Body<'_> {
+    fn u32(&mut self) -> Option<u32> {
+        let v = self.bytes.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some(u32::from_le_bytes(v.try_into().unwrap()))
+    }
+
+    /// Reads a string or array. Strings with length 0 are null.
+    fn array(&mut self) -> Option<Option<&[u8]>> {
+        let len = self.u32()? as usize;
+        if len == 0 {
+            return Some(None);
+        }
+        let data = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len.next_multiple_of(4);
+        Some(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Decoder, Sender},
+        crate::objects::Index,
+        std::{collections::HashMap, fs, path::Path},
+        wayland_db::{
+            db::{Model, Repo},
+            parser::parse,
+        },
+    };
+
+    /// Returns a model that contains only the wayland protocol.
+    fn model() -> Model {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/wayland.xml");
+        let input = fs::read(&path).unwrap();
+        Model {
+            repos: vec![Repo {
+                name: "wayland".to_string(),
+                url: String::new(),
+                protocols: parse(Path::new("protocol/wayland.xml"), &input).unwrap(),
+            }],
+            arg_interfaces: HashMap::new(),
+            arg_enums: HashMap::new(),
+        }
+    }
+
+    /// Encodes a message with its header.
+    fn message(object_id: u32, opcode: u16, args: &[&[u8]]) -> Vec<u8> {
+        let body = args.concat();
+        let size = 8 + body.len() as u32;
+        let mut res = object_id.to_le_bytes().to_vec();
+        res.extend_from_slice(&(size << 16 | opcode as u32).to_le_bytes());
+        res.extend_from_slice(&body);
+        res
+    }
+
+    fn uint(v: u32) -> Vec<u8> {
+        v.to_le_bytes().to_vec()
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut res = uint(s.len() as u32 + 1);
+        res.extend_from_slice(s.as_bytes());
+        res.push(0);
+        res.resize(res.len().next_multiple_of(4), 0);
+        res
+    }
+
+    /// Feeds the chunks to a decoder and returns the decoded messages as text.
+    fn decode(fds: &[(Sender, i32)], chunks: &[(Sender, &[u8])]) -> Vec<String> {
+        let model = model();
+        let index = Index::new(&model, &[]);
+        let mut decoder = Decoder::new(&index);
+        for &(sender, fd) in fds {
+            decoder.streams[sender].fds.push_back(fd);
+        }
+        let mut res = vec![];
+        for &(sender, bytes) in chunks {
+            res.extend(decoder.feed(sender, bytes));
+        }
+        res.extend(decoder.finish());
+        res.iter().map(|m| m.text()).collect()
+    }
+
+    fn get_registry() -> Vec<u8> {
+        message(1, 1, &[&uint(2)])
+    }
+
+    const GET_REGISTRY: &str = "[       0] -> wayland/wayland: wl_display#1 v1 request get_registry(registry=new wl_registry#2 v1)";
+
+    #[test]
+    fn framing() {
+        let sync = message(1, 0, &[&uint(3)]);
+        let messages = [get_registry(), sync].concat();
+        let expected = [
+            GET_REGISTRY,
+            "[      12] -> wayland/wayland: wl_display#1 v1 request sync(callback=new wl_callback#3 v1)",
+        ];
+        assert_eq!(decode(&[], &[(Sender::Client, &messages)]), expected);
+        let bytes: Vec<_> = messages.chunks(1).map(|b| (Sender::Client, b)).collect();
+        assert_eq!(decode(&[], &bytes), expected);
+    }
+
+    #[test]
+    fn truncation() {
+        let get_registry = get_registry();
+        assert_eq!(
+            decode(&[], &[(Sender::Client, &get_registry[..10])]),
+            ["[       0] -> #1 opcode 1 (12 bytes)\n    \
+                 warning: the stream ends with an incomplete message of 10 bytes"],
+        );
+        assert_eq!(
+            decode(&[], &[(Sender::Client, &message(1, 1, &[]))]),
+            [
+                "[       0] -> wayland/wayland: wl_display#1 v1 request get_registry()\n    \
+                 warning: the message is truncated"
+            ],
+        );
+        assert_eq!(
+            decode(
+                &[],
+                &[(Sender::Client, &message(1, 1, &[&uint(2), &uint(0)]))]
+            ),
+            [
+                "[       0] -> wayland/wayland: wl_display#1 v1 request get_registry(registry=new wl_registry#2 v1)\n    \
+                 warning: the message has 4 unused bytes"
+            ],
+        );
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for size in [4, 10] {
+            let mut messages = [get_registry(), get_registry()].concat();
+            messages[12 + 6..12 + 8].copy_from_slice(&(size as u16).to_le_bytes());
+            let expected = [
+                GET_REGISTRY.to_string(),
+                format!(
+                    "[      12] -> #1 opcode 1 ({size} bytes)\n    \
+                     warning: invalid message size {size}, skipping the rest of the stream"
+                ),
+            ];
+            let chunks = [
+                (Sender::Client, &messages[..]),
+                (Sender::Client, &get_registry()),
+            ];
+            assert_eq!(decode(&[], &chunks), expected);
+        }
+    }
+
+    #[test]
+    fn fd_queues() {
+        let global = message(2, 0, &[&uint(1), &string("wl_shm"), &uint(1)]);
+        let bind = message(2, 0, &[&uint(1), &string("wl_shm"), &uint(1), &uint(3)]);
+        let create_pool = |id| message(3, 0, &[&uint(id), &uint(4096)]);
+        let client = [bind, create_pool(4), create_pool(5)].concat();
+        let chunks = [
+            (Sender::Client, &get_registry()[..]),
+            (Sender::Server, &global),
+            (Sender::Client, &client),
+        ];
+        assert_eq!(
+            decode(&[(Sender::Server, 5), (Sender::Client, 17)], &chunks),
+            [
+                GET_REGISTRY,
+                "[       0] <- wayland/wayland: wl_registry#2 v1 event global(name=1, interface=\"wl_shm\", version=1)",
+                "[      12] -> wayland/wayland: wl_registry#2 v1 request bind(name=1, id=new wl_shm#3 v1)",
+                "[      44] -> wayland/wayland: wl_shm#3 v1 request create_pool(id=new wl_shm_pool#4 v1, fd=fd 17, size=4096)",
+                "[      60] -> wayland/wayland: wl_shm#3 v1 request create_pool(id=new wl_shm_pool#5 v1, fd=fd, size=4096)\n    \
+                 warning: no file descriptor annotated for fd",
+            ],
+        );
+    }
+
+    #[test]
+    fn delete_id() {
+        let sync = message(1, 0, &[&uint(3)]);
+        let done = message(3, 0, &[&uint(0)]);
+        let delete_id = |id| message(1, 1, &[&uint(id)]);
+        let server = [done.clone(), delete_id(3), done, delete_id(9)].concat();
+        assert_eq!(
+            decode(&[], &[(Sender::Client, &sync), (Sender::Server, &server)]),
+            [
+                "[       0] -> wayland/wayland: wl_display#1 v1 request sync(callback=new wl_callback#3 v1)",
+                "[       0] <- wayland/wayland: wl_callback#3 v1 event done(callback_data=0)",
+                "[      12] <- wayland/wayland: wl_display#1 v1 event delete_id(id=3)",
+                "[      24] <- #3 opcode 0 (12 bytes)\n    warning: #3 is used after its ID was deleted, skipping the message",
+                "[      36] <- wayland/wayland: wl_display#1 v1 event delete_id(id=9)\n    \
+                 warning: delete_id for unknown object #9",
+            ],
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use {
+    clap::Args,
+    rusqlite::{Connection, OpenFlags, types::ValueRef},
+    serde_json::{Map, Value},
+    std::{
+        fmt::Write as _,
+        fs,
+        io::{self, BufWriter, Write},
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum DumpError {
+    #[error("could not open {}", .0.display())]
+    OpenDb(PathBuf, #[source] rusqlite::Error),
+    #[error("could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("could not query the {0} table")]
+    Query(&'static str, #[source] rusqlite::Error),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct DumpArgs {
+    /// The database to dump.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The directory to write the `<table>.jsonl` files to.
+    #[clap(long, default_value = "dump")]
+    output: PathBuf,
+}
+
+/// The tables of the dump.
+///
+/// Surrogate IDs are replaced by the names of the referenced objects so that
+/// adding a protocol does not change the lines of unrelated objects. Rows are
+/// sorted by these names and, for children that are ordered in the XML, by their
+/// position.
+// language=sqlite
+const TABLES: &[(&str, &str)] = &[
+    (
+        "generator",
+        "select version, timestamp, fingerprint
+         from generator",
+    ),
+    (
+        "repo",
+        "select name repo, url
+         from repo
+         order by name",
+    ),
+    (
+        "file",
+        "select r.name repo, f.path, f.size, f.hash
+         from file f
+         join repo r using (repo_id)
+         order by r.name, f.path",
+    ),
+    (
+        "protocol",
+        "select
+             r.name repo,
+             p.name protocol,
+             p.path,
+             p.copyright,
+             d.summary description_summary,
+             d.body description
+         from protocol p
+         join repo r using (repo_id)
+         left join description d using (description_id)
+         order by r.name, p.name",
+    ),
+    (
+        "interface",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             i.version,
+             d.summary description_summary,
+             d.body description,
+             i.is_global,
+             i.global_reason
+         from interface i
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         left join description d on d.description_id = i.description_id
+         order by r.name, p.name, i.name",
+    ),
+    (
+        "enum",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             e.name enum,
+             e.since,
+             e.is_bitfield,
+             d.summary description_summary,
+             d.body description
+         from enum e
+         join interface i using (interface_id)
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         left join description d on d.description_id = e.description_id
+         order by r.name, p.name, i.name, e.name",
+    ),
+    (
+        "entry",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             e.name enum,
+             n.name entry,
+             n.value_str,
+             n.value,
+             n.summary,
+             n.since,
+             n.deprecated_since,
+             d.summary description_summary,
+             d.body description
+         from entry n
+         join enum e using (enum_id)
+         join interface i using (interface_id)
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         left join description d on d.description_id = n.description_id
+         order by r.name, p.name, i.name, e.name, n.entry_id",
+    ),
+    (
+        "message",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.number,
+             m.name message,
+             m.is_destructor,
+             m.since,
+             m.deprecated_since,
+             d.summary description_summary,
+             d.body description,
+             m.signature
+         from message m
+         join interface i using (interface_id)
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         left join description d on d.description_id = m.description_id
+         order by r.name, p.name, i.name, m.is_request desc, m.number",
+    ),
+    (
+        "arg",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.name message,
+             a.position,
+             a.name arg,
+             t.name type,
+             a.summary,
+             a.interface_name,
+             a.allow_null,
+             a.enum_name,
+             d.summary description_summary,
+             d.body description
+         from arg a
+         join type t using (type_id)
+         join message m using (message_id)
+         join interface i using (interface_id)
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         left join description d on d.description_id = a.description_id
+         order by r.name, p.name, i.name, m.is_request desc, m.number, a.position",
+    ),
+    (
+        "wire_arg",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.name message,
+             w.position,
+             a.name arg,
+             t.name type,
+             w.allow_null
+         from wire_arg w
+         join arg a using (arg_id)
+         join type t on t.type_id = w.type_id
+         join message m on m.message_id = w.message_id
+         join interface i using (interface_id)
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         order by r.name, p.name, i.name, m.is_request desc, m.number, w.position",
+    ),
+    (
+        "rel_arg_interface",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.name message,
+             a.name arg,
+             r2.name target_repo,
+             p2.name target_protocol,
+             i2.name target_interface
+         from rel_arg_interface rai
+         join arg a using (arg_id)
+         join message m using (message_id)
+         join interface i on i.interface_id = m.interface_id
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         join interface i2 on i2.interface_id = rai.interface_id
+         join protocol p2 on p2.protocol_id = i2.protocol_id
+         join repo r2 on r2.repo_id = p2.repo_id
+         order by r.name, p.name, i.name, m.is_request desc, m.number, a.position,
+                  r2.name, p2.name, i2.name",
+    ),
+    (
+        "rel_arg_enum",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.name message,
+             a.name arg,
+             r2.name target_repo,
+             p2.name target_protocol,
+             i2.name target_interface,
+             e.name target_enum
+         from rel_arg_enum rae
+         join arg a using (arg_id)
+         join message m using (message_id)
+         join interface i on i.interface_id = m.interface_id
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         join enum e on e.enum_id = rae.enum_id
+         join interface i2 on i2.interface_id = e.interface_id
+         join protocol p2 on p2.protocol_id = i2.protocol_id
+         join repo r2 on r2.repo_id = p2.repo_id
+         order by r.name, p.name, i.name, m.is_request desc, m.number, a.position,
+                  r2.name, p2.name, i2.name, e.name",
+    ),
+    (
+        "object_factory",
+        "select
+             r.name repo,
+             p.name protocol,
+             i.name interface,
+             m.is_request,
+             m.name message,
+             a.name arg,
+             r2.name child_repo,
+             p2.name child_protocol,
+             i2.name child_interface,
+             f.since
+         from object_factory f
+         join arg a using (arg_id)
+         join message m on m.message_id = f.message_id
+         join interface i on i.interface_id = f.parent_interface_id
+         join protocol p using (protocol_id)
+         join repo r using (repo_id)
+         join interface i2 on i2.interface_id = f.child_interface_id
+         join protocol p2 on p2.protocol_id = i2.protocol_id
+         join repo r2 on r2.repo_id = p2.repo_id
+         order by r.name, p.name, i.name, m.is_request desc, m.number, a.position,
+                  r2.name, p2.name, i2.name",
+    ),
+    (
+        "lint_rule",
+        "select name rule, severity, description
+         from lint_rule
+         order by name",
+    ),
+    (
+        "lint_finding",
+        "select r.name repo, f.path, l.name rule, n.location, n.message
+         from lint_finding n
+         join lint_rule l using (lint_rule_id)
+         join file f using (file_id)
+         join repo r using (repo_id)
+         order by r.name, f.path, n.lint_finding_id",
+    ),
+];
+
+pub fn main(args: DumpArgs) -> Result<(), DumpError> {
+    let db = Connection::open_with_flags(&args.db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| DumpError::OpenDb(args.db.clone(), e))?;
+    fs::create_dir_all(&args.output).map_err(|e| DumpError::CreateDir(args.output.clone(), e))?;
+    for &(table, query) in TABLES {
+        let path = args.output.join(format!("{table}.jsonl"));
+        let lines = dump_table(&db, query).map_err(|e| DumpError::Query(table, e))?;
+        write_lines(&path, &lines).map_err(|e| DumpError::Write(path, e))?;
+    }
+    Ok(())
+}
+
+fn dump_table(db: &Connection, query: &str) -> Result<Vec<String>, rusqlite::Error> {
+    let mut stmt = db.prepare(query)?;
+    let names: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(|n| n.to_owned())
+        .collect();
+    let mut rows = stmt.query([])?;
+    let mut lines = vec![];
+    while let Some(row) = rows.next()? {
+        let mut object = Map::new();
+        for (idx, name) in names.iter().enumerate() {
+            let value = match row.get_ref(idx)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(i) => i.into(),
+                ValueRef::Real(f) => f.into(),
+                ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+                ValueRef::Blob(b) => {
+                    let mut hex = String::with_capacity(2 * b.len());
+                    for b in b {
+                        let _ = write!(hex, "{b:02x}");
+                    }
+                    hex.into()
+                }
+            };
+            object.insert(name.clone(), value);
+        }
+        lines.push(Value::Object(object).to_string());
+    }
+    Ok(lines)
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> Result<(), io::Error> {
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    for line in lines {
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+    }
+    file.flush()
+}
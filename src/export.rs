@@ -0,0 +1,388 @@
+use {
+    clap::{Args, ValueEnum},
+    serde::Serialize,
+    std::{
+        fs,
+        io::{self, BufWriter, Write},
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, Description, Entry, Enum, Interface, Message, MessageType, Protocol},
+        db::{self, Database, DbError, EnumRef, InterfaceRef, Model},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("could not load the database")]
+    Load(#[from] DbError),
+    #[error("could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("could not write to stdout")]
+    WriteStdout(#[source] io::Error),
+    #[error("`{0}` cannot be used as a file name")]
+    FileName(String),
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The output format.
+    #[clap(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Write a single file or one file per protocol.
+    #[clap(long, value_enum, default_value_t = Layout::Single)]
+    layout: Layout,
+    /// The database to export.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The output file for the single layout (default: stdout) or the output
+    /// directory for the per-protocol layout.
+    #[clap(long, required_if_eq("layout", "per-protocol"))]
+    output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Format {
+    /// JSON as described by `export-schema.json`.
+    Json,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq)]
+enum Layout {
+    /// A single document containing all repositories.
+    Single,
+    /// One `<repo>/<protocol>.json` document per protocol.
+    PerProtocol,
+}
+
+pub fn main(args: ExportArgs) -> Result<(), ExportError> {
+    let model = Database::open(&args.db)?.load()?;
+    match args.format {
+        Format::Json => export_json(&args, &model),
+    }
+}
+
+/// Converts the model to the `repos` array of the JSON export.
+pub(crate) fn json_repos(model: &Model) -> Vec<JsonRepo<'_>> {
+    model
+        .repos
+        .iter()
+        .map(|repo| JsonRepo {
+            name: &repo.name,
+            url: &repo.url,
+            protocols: repo
+                .protocols
+                .iter()
+                .map(|p| JsonProtocol::new(model, repo, p))
+                .collect(),
+        })
+        .collect()
+}
+
+fn export_json(args: &ExportArgs, model: &Model) -> Result<(), ExportError> {
+    let repos = json_repos(model);
+    if args.layout == Layout::Single {
+        let document = JsonDocument { repos };
+        return match &args.output {
+            Some(path) => write_json(path, &document),
+            None => {
+                let mut stdout = io::stdout().lock();
+                serde_json::to_writer_pretty(&mut stdout, &document)
+                    .map_err(io::Error::from)
+                    .and_then(|_| stdout.write_all(b"\n"))
+                    .map_err(ExportError::WriteStdout)
+            }
+        };
+    }
+    let dir = args.output.as_deref().unwrap();
+    for repo in repos {
+        if !is_file_name(repo.name) {
+            return Err(ExportError::FileName(repo.name.to_string()));
+        }
+        let repo_dir = dir.join(repo.name);
+        fs::create_dir_all(&repo_dir).map_err(|e| ExportError::CreateDir(repo_dir.clone(), e))?;
+        for protocol in repo.protocols {
+            if !is_file_name(protocol.name) {
+                return Err(ExportError::FileName(protocol.name.to_string()));
+            }
+            let document = JsonProtocolDocument {
+                repo: JsonRepoHeader {
+                    name: repo.name,
+                    url: repo.url,
+                },
+                protocol,
+            };
+            let path = repo_dir.join(format!("{}.json", document.protocol.name));
+            write_json(&path, &document)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether a name from the database can be used as the name of a file
+/// or directory in an output directory.
+///
+/// Names with path separators or `..` could refer to files outside of the
+/// output directory.
+pub(crate) fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && !name.contains("..") && !name.contains(['/', '\\', '\0'])
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), ExportError> {
+    (|| {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, value)?;
+        file.write_all(b"\n")?;
+        file.flush()
+    })()
+    .map_err(|e| ExportError::Write(path.to_path_buf(), e))
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    repos: Vec<JsonRepo<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonProtocolDocument<'a> {
+    repo: JsonRepoHeader<'a>,
+    protocol: JsonProtocol<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonRepoHeader<'a> {
+    name: &'a str,
+    url: &'a str,
+}
+
+#[derive(Serialize)]
+pub(crate) struct JsonRepo<'a> {
+    name: &'a str,
+    url: &'a str,
+    protocols: Vec<JsonProtocol<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonProtocol<'a> {
+    name: &'a str,
+    path: &'a str,
+    copyright: Option<&'a str>,
+    description: Option<JsonDescription<'a>>,
+    interfaces: Vec<JsonInterface<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonDescription<'a> {
+    summary: Option<&'a str>,
+    body: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonInterface<'a> {
+    name: &'a str,
+    version: u32,
+    description: Option<JsonDescription<'a>>,
+    requests: Vec<JsonMessage<'a>>,
+    events: Vec<JsonMessage<'a>>,
+    enums: Vec<JsonEnum<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+    name: &'a str,
+    opcode: usize,
+    destructor: bool,
+    since: Option<u32>,
+    deprecated_since: Option<u32>,
+    description: Option<JsonDescription<'a>>,
+    signature: String,
+    args: Vec<JsonArg<'a>>,
+    wire_args: Vec<JsonWireArg>,
+}
+
+#[derive(Serialize)]
+struct JsonArg<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    summary: Option<&'a str>,
+    description: Option<JsonDescription<'a>>,
+    interface: Option<&'a str>,
+    allow_null: bool,
+    #[serde(rename = "enum")]
+    enum_: Option<&'a str>,
+    resolved_interfaces: &'a [InterfaceRef],
+    resolved_enums: &'a [EnumRef],
+}
+
+#[derive(Serialize)]
+struct JsonWireArg {
+    arg: usize,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    allow_null: bool,
+}
+
+#[derive(Serialize)]
+struct JsonEnum<'a> {
+    name: &'a str,
+    since: Option<u32>,
+    bitfield: bool,
+    description: Option<JsonDescription<'a>>,
+    entries: Vec<JsonEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    name: &'a str,
+    value: i64,
+    value_str: &'a str,
+    summary: Option<&'a str>,
+    since: Option<u32>,
+    deprecated_since: Option<u32>,
+    description: Option<JsonDescription<'a>>,
+}
+
+impl<'a> JsonProtocol<'a> {
+    fn new(model: &'a Model, repo: &'a db::Repo, protocol: &'a Protocol) -> Self {
+        Self {
+            name: &protocol.name,
+            path: &protocol.path,
+            copyright: protocol.copyright.as_ref().map(|c| &*c.body),
+            description: JsonDescription::new(&protocol.description),
+            interfaces: protocol
+                .interfaces
+                .iter()
+                .map(|i| JsonInterface::new(model, repo, protocol, i))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> JsonDescription<'a> {
+    fn new(description: &'a Option<Description>) -> Option<Self> {
+        description.as_ref().map(|d| Self {
+            summary: d.summary.as_deref(),
+            body: &d.body,
+        })
+    }
+}
+
+impl<'a> JsonInterface<'a> {
+    fn new(
+        model: &'a Model,
+        repo: &'a db::Repo,
+        protocol: &'a Protocol,
+        interface: &'a Interface,
+    ) -> Self {
+        let messages = |is_request: bool| {
+            interface
+                .messages_of_kind(is_request)
+                .map(|m| JsonMessage::new(model, repo, protocol, interface, m))
+                .collect()
+        };
+        Self {
+            name: &interface.name,
+            version: interface.version,
+            description: JsonDescription::new(&interface.description),
+            requests: messages(true),
+            events: messages(false),
+            enums: interface.enums.iter().map(JsonEnum::new).collect(),
+        }
+    }
+}
+
+impl<'a> JsonMessage<'a> {
+    fn new(
+        model: &'a Model,
+        repo: &'a db::Repo,
+        protocol: &'a Protocol,
+        interface: &'a Interface,
+        message: &'a Message,
+    ) -> Self {
+        Self {
+            name: &message.name,
+            opcode: message.message_id,
+            destructor: message.ty == Some(MessageType::Destructor),
+            since: message.since,
+            deprecated_since: message.deprecated_since,
+            description: JsonDescription::new(&message.description),
+            signature: message.signature(),
+            args: message
+                .args
+                .iter()
+                .map(|a| JsonArg::new(model, repo, protocol, interface, message, a))
+                .collect(),
+            wire_args: message
+                .wire_args()
+                .into_iter()
+                .map(|a| JsonWireArg {
+                    arg: a.arg,
+                    ty: a.ty.name(),
+                    allow_null: a.allow_null,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> JsonArg<'a> {
+    fn new(
+        model: &'a Model,
+        repo: &'a db::Repo,
+        protocol: &'a Protocol,
+        interface: &'a Interface,
+        message: &'a Message,
+        arg: &'a Arg,
+    ) -> Self {
+        let path = Model::arg_path(repo, protocol, interface, message, arg);
+        Self {
+            name: &arg.name,
+            ty: arg.ty.name(),
+            summary: arg.summary.as_deref(),
+            description: JsonDescription::new(&arg.description),
+            interface: arg.interface.as_deref(),
+            allow_null: arg.allow_null,
+            enum_: arg.enum_.as_deref(),
+            resolved_interfaces: model
+                .arg_interfaces
+                .get(&path)
+                .map(|v| &v[..])
+                .unwrap_or_default(),
+            resolved_enums: model
+                .arg_enums
+                .get(&path)
+                .map(|v| &v[..])
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a> JsonEnum<'a> {
+    fn new(enum_: &'a Enum) -> Self {
+        Self {
+            name: &enum_.name,
+            since: enum_.since,
+            bitfield: enum_.bitfield,
+            description: JsonDescription::new(&enum_.description),
+            entries: enum_.entries.iter().map(JsonEntry::new).collect(),
+        }
+    }
+}
+
+impl<'a> JsonEntry<'a> {
+    fn new(entry: &'a Entry) -> Self {
+        Self {
+            name: &entry.name,
+            value: entry.value_i64,
+            value_str: &entry.value,
+            summary: entry.summary.as_deref(),
+            since: entry.since,
+            deprecated_since: entry.deprecated_since,
+            description: JsonDescription::new(&entry.description),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use {
+    crate::collector::find_xml_files,
+    clap::Args,
+    error_reporter::Report,
+    quick_xml::{Reader, events::Event},
+    std::{
+        fs, io,
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+    wayland_db::{
+        parser::{ParserError, parse},
+        writer::write,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum FmtError {
+    #[error("could not search for protocol files")]
+    Walk(#[source] walkdir::Error),
+    #[error("{0} files could not be formatted")]
+    Failed(usize),
+    #[error("{0} files are not formatted")]
+    Unformatted(usize),
+}
+
+#[derive(Debug, Error)]
+enum FileError {
+    #[error("could not read the file")]
+    Read(#[source] io::Error),
+    #[error("could not parse the file")]
+    Parse(#[source] ParserError),
+    #[error("the file contains {0} protocols instead of 1")]
+    ProtocolCount(usize),
+    #[error("the file contains comments that would be lost")]
+    Comments,
+    #[error("could not write the file")]
+    Write(#[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct FmtArgs {
+    /// List the files that are not formatted instead of rewriting them.
+    #[clap(long)]
+    check: bool,
+    /// The protocol files to format. Directories are searched for `.xml` files.
+    #[clap(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn main(args: FmtArgs) -> Result<(), FmtError> {
+    let files = find_xml_files(&args.paths).map_err(FmtError::Walk)?;
+    let mut failed = 0;
+    let mut unformatted = 0;
+    for file in &files {
+        match format_file(file, args.check) {
+            Ok(true) => {}
+            Ok(false) => {
+                unformatted += 1;
+                if args.check {
+                    println!("{}", file.display());
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Could not format {}: {}", file.display(), Report::new(e));
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(FmtError::Failed(failed));
+    }
+    if args.check && unformatted > 0 {
+        return Err(FmtError::Unformatted(unformatted));
+    }
+    Ok(())
+}
+
+/// Formats a file and returns whether it was already formatted.
+///
+/// In check mode, the file is not modified.
+fn format_file(path: &Path, check: bool) -> Result<bool, FileError> {
+    let input = fs::read(path).map_err(FileError::Read)?;
+    let mut protocols = parse(path, &input).map_err(FileError::Parse)?;
+    if protocols.len() != 1 {
+        return Err(FileError::ProtocolCount(protocols.len()));
+    }
+    let output = write(&protocols.pop().unwrap());
+    if output.as_bytes() == input {
+        return Ok(true);
+    }
+    if has_comments(&input) {
+        return Err(FileError::Comments);
+    }
+    if !check {
+        fs::write(path, output).map_err(FileError::Write)?;
+    }
+    Ok(false)
+}
+
+/// Returns whether the file contains comments, which are not part of the AST.
+fn has_comments(input: &[u8]) -> bool {
+    let mut reader = Reader::from_reader(input);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Comment(_)) => return true,
+            Ok(Event::Eof) | Err(_) => return false,
+            _ => {}
+        }
+    }
+}
@@ -1,19 +1,66 @@
 use {
     crate::{
-        ast::{ArgType, Description, Interface, MessageType},
-        collector::collect,
+        collector::{File, Repo, collect, parse_files},
+        lint::{Rule, lint},
+    },
+    clap::Args,
+    error_reporter::Report,
+    linearize::{LinearizeExt, StaticMap, static_map},
+    rusqlite::{CachedStatement, Connection, ErrorCode, Transaction, config::DbConfig, params},
+    sha2::{Digest, Sha256},
+    std::{
+        collections::HashMap,
+        env,
+        error::Error as _,
+        ffi::OsString,
+        fmt::Write as _,
+        fs,
+        io::{self, ErrorKind},
     },
-    indexmap::IndexMap,
-    linearize::{StaticMap, static_map},
-    rusqlite::{Transaction, config::DbConfig, params},
-    std::collections::HashMap,
     thiserror::Error,
+    wayland_db::{
+        ast::{ArgType, Description, Enum, Interface, Message, MessageType, Protocol},
+        db::SCHEMA_VERSION,
+        text::format_ml_text,
+    },
 };
 
 #[derive(Debug, Error)]
 pub enum GeneratorError {
-    #[error("could not open {}", WAYLAND_DB)]
-    OpenWaylandDb(#[source] rusqlite::Error),
+    #[error("could not open {0}")]
+    OpenWaylandDb(&'static str, #[source] rusqlite::Error),
+    #[error("could not copy {} to {}", WAYLAND_DB, WAYLAND_DB_TMP)]
+    CopyWaylandDb(#[source] io::Error),
+    #[error("could not remove {0}")]
+    RemoveTmp(&'static str, #[source] io::Error),
+    #[error("could not rename {} to {}", WAYLAND_DB_VACUUM, WAYLAND_DB_TMP)]
+    RenameVacuum(#[source] io::Error),
+    #[error("could not rename {} to {}", WAYLAND_DB_TMP, WAYLAND_DB)]
+    RenameWaylandDb(#[source] io::Error),
+    #[error("could not read a database file")]
+    ReadWaylandDb(#[source] io::Error),
+    #[error("{} differs from a fresh build", WAYLAND_DB)]
+    NotReproducible,
+    #[error("SOURCE_DATE_EPOCH ({0:?}) is not a valid integer")]
+    SourceDateEpoch(OsString),
+    #[error("could not insert the generator row")]
+    InsertGenerator(#[source] rusqlite::Error),
+    #[error("could not close the database")]
+    CloseDb(#[source] rusqlite::Error),
+    #[error("could not enable foreign keys")]
+    EnableForeignKeys(#[source] rusqlite::Error),
+    #[error("could not manage a savepoint")]
+    Savepoint(#[source] rusqlite::Error),
+    #[error("could not run the foreign key check")]
+    RunForeignKeyCheck(#[source] rusqlite::Error),
+    #[error("the foreign key check failed:\n{0}")]
+    ForeignKeyCheck(String),
+    #[error("could not run the integrity check")]
+    RunIntegrityCheck(#[source] rusqlite::Error),
+    #[error("the integrity check failed:\n{0}")]
+    IntegrityCheck(String),
+    #[error("the protocols of some files violate constraints of the schema:\n{0}")]
+    InvalidFiles(String),
     #[error("could not create a transaction")]
     CreateTransaction(#[source] rusqlite::Error),
     #[error("could not commit a transaction")]
@@ -24,10 +71,22 @@ pub enum GeneratorError {
     CreateSchema(#[source] rusqlite::Error),
     #[error("could not prepare statement {0}")]
     PrepareStatement(&'static str, #[source] rusqlite::Error),
+    #[error("could not read the schema version")]
+    ReadSchema(#[source] rusqlite::Error),
+    #[error("could not load the existing contents of the database")]
+    LoadExisting(#[source] rusqlite::Error),
+    #[error("could not delete stale rows")]
+    DeleteStale(#[source] rusqlite::Error),
+    #[error("could not link args to interfaces and enums")]
+    Link(#[source] rusqlite::Error),
+    #[error("could not classify the global interfaces")]
+    ClassifyGlobals(#[source] rusqlite::Error),
     #[error("could not create a type")]
     InsertType(#[source] rusqlite::Error),
     #[error("could not insert a repo")]
     InsertRepo(#[source] rusqlite::Error),
+    #[error("could not insert a file")]
+    InsertFile(#[source] rusqlite::Error),
     #[error("could not insert a description")]
     InsertDescription(#[source] rusqlite::Error),
     #[error("could not insert a protocol")]
@@ -42,419 +101,1030 @@ pub enum GeneratorError {
     InsertMessage(#[source] rusqlite::Error),
     #[error("could not insert an arg")]
     InsertArg(#[source] rusqlite::Error),
-    #[error("could not insert a rel_arg_interface")]
-    InsertRelArgInterface(#[source] rusqlite::Error),
-    #[error("could not insert a rel_arg_enum")]
-    InsertRelArgEnum(#[source] rusqlite::Error),
+    #[error("could not insert a wire arg")]
+    InsertWireArg(#[source] rusqlite::Error),
+    #[error("could not insert a lint rule")]
+    InsertLintRule(#[source] rusqlite::Error),
+    #[error("could not insert a lint finding")]
+    InsertLintFinding(#[source] rusqlite::Error),
     #[error("could not optimize the database")]
     OptimizeDatabase(#[source] rusqlite::Error),
 }
 
+impl GeneratorError {
+    fn is_constraint_violation(&self) -> bool {
+        let mut source = self.source();
+        while let Some(e) = source {
+            if let Some(e) = e.downcast_ref::<rusqlite::Error>() {
+                return e.sqlite_error_code() == Some(ErrorCode::ConstraintViolation);
+            }
+            source = e.source();
+        }
+        false
+    }
+}
+
 const WAYLAND_DB: &str = "wayland.db";
+const WAYLAND_DB_TMP: &str = "wayland.db.tmp";
+const WAYLAND_DB_VACUUM: &str = "wayland.db.vacuum";
+
+#[derive(Args, Debug, Default)]
+pub struct GenerateArgs {
+    /// Only reparse files whose contents changed since the last run.
+    ///
+    /// Falls back to a full rebuild if the database does not contain a file
+    /// table.
+    #[clap(long, conflicts_with = "check")]
+    incremental: bool,
+    /// Build the database and check that it is byte-identical to the existing
+    /// database instead of replacing it.
+    #[clap(long)]
+    check: bool,
+    /// Skip the protocols of files that violate constraints of the schema
+    /// instead of failing.
+    #[clap(long)]
+    skip_invalid: bool,
+}
 
-pub fn main() -> Result<(), GeneratorError> {
-    let mut db = rusqlite::Connection::open(WAYLAND_DB).map_err(GeneratorError::OpenWaylandDb)?;
-    (|| {
-        db.set_db_config(DbConfig::SQLITE_DBCONFIG_RESET_DATABASE, true)?;
-        db.execute_batch("vacuum")?;
-        db.set_db_config(DbConfig::SQLITE_DBCONFIG_RESET_DATABASE, false)
-    })()
-    .map_err(GeneratorError::ResetDb)?;
+pub fn main(args: GenerateArgs) -> Result<(), GeneratorError> {
+    remove_tmp()?;
+    if args.incremental
+        && let Err(e) = fs::copy(WAYLAND_DB, WAYLAND_DB_TMP)
+        && e.kind() != ErrorKind::NotFound
+    {
+        return Err(GeneratorError::CopyWaylandDb(e));
+    }
+    if let Err(e) = build(&args) {
+        let _ = remove_tmp();
+        return Err(e);
+    }
+    if args.check {
+        let res = compare_to_existing();
+        let _ = remove_tmp();
+        return res;
+    }
+    fs::rename(WAYLAND_DB_TMP, WAYLAND_DB).map_err(GeneratorError::RenameWaylandDb)
+}
+
+fn compare_to_existing() -> Result<(), GeneratorError> {
+    let existing = fs::read(WAYLAND_DB).map_err(GeneratorError::ReadWaylandDb)?;
+    let new = fs::read(WAYLAND_DB_TMP).map_err(GeneratorError::ReadWaylandDb)?;
+    if existing != new {
+        return Err(GeneratorError::NotReproducible);
+    }
+    Ok(())
+}
+
+fn remove_tmp() -> Result<(), GeneratorError> {
+    for path in [WAYLAND_DB_TMP, WAYLAND_DB_VACUUM] {
+        if let Err(e) = fs::remove_file(path)
+            && e.kind() != ErrorKind::NotFound
+        {
+            return Err(GeneratorError::RemoveTmp(path, e));
+        }
+    }
+    Ok(())
+}
+
+/// Builds the database in [`WAYLAND_DB_TMP`].
+///
+/// Readers of [`WAYLAND_DB`] never observe a partially built database since the
+/// file is only renamed after this function succeeds.
+fn build(args: &GenerateArgs) -> Result<(), GeneratorError> {
+    let mut db = rusqlite::Connection::open(WAYLAND_DB_TMP)
+        .map_err(|e| GeneratorError::OpenWaylandDb(WAYLAND_DB_TMP, e))?;
+    db.execute_batch("pragma foreign_keys = on")
+        .map_err(GeneratorError::EnableForeignKeys)?;
+    let fingerprint = fingerprint();
+    let incremental = args.incremental && can_update(&db, &fingerprint)?;
+    if !incremental {
+        (|| {
+            db.set_db_config(DbConfig::SQLITE_DBCONFIG_RESET_DATABASE, true)?;
+            // Don't depend on the compile-time default of the sqlite library.
+            db.execute_batch("pragma page_size = 4096; vacuum")?;
+            db.set_db_config(DbConfig::SQLITE_DBCONFIG_RESET_DATABASE, false)
+        })()
+        .map_err(GeneratorError::ResetDb)?;
+    }
     let tx = db
         .transaction()
         .map_err(GeneratorError::CreateTransaction)?;
-    insert(&tx)?;
+    let repos = collect();
+    let invalid = match incremental {
+        true => update(&tx, &repos)?,
+        false => insert(&tx, &repos)?,
+    };
+    if !invalid.is_empty() {
+        if !args.skip_invalid {
+            return Err(GeneratorError::InvalidFiles(invalid.join("\n")));
+        }
+        for file in invalid {
+            eprintln!("Skipped the protocols of {file}");
+        }
+    }
+    link(&tx)?;
+    classify_globals(&tx, &repos)?;
+    let timestamp = timestamp(&repos)?;
+    // language=sqlite
+    tx.execute("delete from generator", [])
+        .and_then(|_| {
+            tx.execute(
+                "insert into generator (version, timestamp, fingerprint) values (?, ?, ?)",
+                params![env!("CARGO_PKG_VERSION"), timestamp, fingerprint],
+            )
+        })
+        .map_err(GeneratorError::InsertGenerator)?;
     tx.commit().map_err(GeneratorError::CommitTransaction)?;
     db.execute_batch("pragma optimize")
         .map_err(GeneratorError::OptimizeDatabase)?;
-    Ok(())
+    check_integrity(&db)?;
+    // Write the contents to a fresh file so that the page layout and the header
+    // do not depend on the history of the file. Otherwise incremental builds
+    // would differ from full builds, e.g. in the file change counter.
+    // language=sqlite
+    db.execute("vacuum into ?", [WAYLAND_DB_VACUUM])
+        .map_err(GeneratorError::OptimizeDatabase)?;
+    db.close().map_err(|(_, e)| GeneratorError::CloseDb(e))?;
+    // `vacuum into` copies the schema cookie, which counts the schema changes
+    // of the old file. The SQLite documentation warns against writing it
+    // because other connections would not notice that their cached schema is
+    // stale. This is safe here: the file was just created by `vacuum into`, no
+    // other connection has opened it yet, and its schema does not change.
+    let db = Connection::open(WAYLAND_DB_VACUUM)
+        .map_err(|e| GeneratorError::OpenWaylandDb(WAYLAND_DB_VACUUM, e))?;
+    db.pragma_update(None, "schema_version", 1)
+        .map_err(GeneratorError::OptimizeDatabase)?;
+    db.close().map_err(|(_, e)| GeneratorError::CloseDb(e))?;
+    fs::rename(WAYLAND_DB_VACUUM, WAYLAND_DB_TMP).map_err(GeneratorError::RenameVacuum)
 }
 
-fn insert(tx: &Transaction<'_>) -> Result<(), GeneratorError> {
-    let repos = collect();
+/// Returns whether the rows of unchanged files in [`WAYLAND_DB_TMP`] can be
+/// reused.
+///
+/// This requires the same schema version, generator version, and fingerprint.
+/// Otherwise the rows might differ from those of a full build.
+fn can_update(db: &Connection, fingerprint: &str) -> Result<bool, GeneratorError> {
+    let version: i64 = db
+        .pragma_query_value(None, "user_version", |r| r.get(0))
+        .map_err(GeneratorError::ReadSchema)?;
+    if version != SCHEMA_VERSION {
+        eprintln!(
+            "{WAYLAND_DB} uses schema version {version} instead of {SCHEMA_VERSION}, \
+             performing a full rebuild",
+        );
+        return Ok(false);
+    }
+    // language=sqlite
+    let (old_version, old_fingerprint): (String, String) = db
+        .query_one("select version, fingerprint from generator", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .map_err(GeneratorError::ReadSchema)?;
+    if old_version != env!("CARGO_PKG_VERSION") {
+        eprintln!(
+            "{WAYLAND_DB} was built by version {old_version} of the generator, \
+             performing a full rebuild",
+        );
+        return Ok(false);
+    }
+    if old_fingerprint != fingerprint {
+        eprintln!(
+            "{WAYLAND_DB} was built by a different parser, lint rules, or generator, \
+             performing a full rebuild",
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
 
-    let mut next_id = 1i64;
-    let mut next_id = || {
-        let id = next_id;
-        next_id += 1;
-        id
-    };
+/// Returns a hash of the code that determines the rows of a file.
+///
+/// The hash covers the parser, the lint rules, and the generator itself, so
+/// that changes to them are not hidden by the reused rows of unchanged files.
+fn fingerprint() -> String {
+    // The rule catalog is part of lint.rs.
+    const SOURCES: &[&str] = &[
+        include_str!("../schema.sql"),
+        include_str!("ast.rs"),
+        include_str!("parser.rs"),
+        include_str!("text.rs"),
+        include_str!("lint.rs"),
+        include_str!("generate.rs"),
+    ];
+    let mut hasher = Sha256::new();
+    for source in SOURCES {
+        hasher.update((source.len() as u64).to_le_bytes());
+        hasher.update(source);
+    }
+    let mut hex = String::with_capacity(64);
+    for b in hasher.finalize() {
+        let _ = write!(hex, "{b:02x}");
+    }
+    hex
+}
 
+/// Returns the timestamp stored in the `generator` table.
+///
+/// This is `SOURCE_DATE_EPOCH` if it is set and the time of the newest commit in
+/// the repositories otherwise.
+fn timestamp(repos: &[Repo]) -> Result<i64, GeneratorError> {
+    if let Some(epoch) = env::var_os("SOURCE_DATE_EPOCH") {
+        return epoch
+            .to_str()
+            .and_then(|e| e.parse().ok())
+            .ok_or(GeneratorError::SourceDateEpoch(epoch));
+    }
+    Ok(repos
+        .iter()
+        .filter_map(|r| r.commit_time)
+        .max()
+        .unwrap_or_default())
+}
+
+fn check_integrity(db: &Connection) -> Result<(), GeneratorError> {
+    // language=sqlite
+    let problems: Vec<String> = db
+        .prepare("pragma integrity_check")
+        .and_then(|mut s| s.query_map([], |r| r.get(0))?.collect())
+        .map_err(GeneratorError::RunIntegrityCheck)?;
+    if problems != ["ok"] {
+        return Err(GeneratorError::IntegrityCheck(problems.join("\n")));
+    }
+    // language=sqlite
+    let problems: Vec<String> = db
+        .prepare("pragma foreign_key_check")
+        .and_then(|mut s| {
+            s.query_map([], |r| {
+                let table: String = r.get(0)?;
+                let rowid: i64 = r.get(1)?;
+                let parent: String = r.get(2)?;
+                Ok(format!(
+                    "row {rowid} of {table} references a missing row of {parent}"
+                ))
+            })?
+            .collect()
+        })
+        .map_err(GeneratorError::RunForeignKeyCheck)?;
+    if !problems.is_empty() {
+        return Err(GeneratorError::ForeignKeyCheck(problems.join("\n")));
+    }
+    Ok(())
+}
+
+/// Inserts all repos into an empty database.
+///
+/// Returns the files whose protocols were skipped because they violate
+/// constraints of the schema.
+fn insert(tx: &Transaction<'_>, repos: &[Repo]) -> Result<Vec<String>, GeneratorError> {
     tx.execute_batch(include_str!("../schema.sql"))
         .map_err(GeneratorError::CreateSchema)?;
+    tx.execute_batch(include_str!("../schema-compat.sql"))
+        .map_err(GeneratorError::CreateSchema)?;
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(GeneratorError::CreateSchema)?;
+
+    let mut inserter = Inserter {
+        tx,
+        next_id: 1,
+        types: static_map!(_ => 0),
+        rules: static_map!(_ => 0),
+        invalid: vec![],
+    };
+    inserter.insert_types()?;
+    inserter.insert_rules()?;
+
+    let files: Vec<_> = repos
+        .iter()
+        .flat_map(|r| r.files.iter().map(move |f| (r, f)))
+        .collect();
+    let mut protocols = parse_files(&files).into_iter();
+    for repo in repos {
+        let repo_id = inserter.insert_repo(repo)?;
+        let mut files: Vec<_> = repo
+            .files
+            .iter()
+            .zip(protocols.by_ref().take(repo.files.len()))
+            .collect();
+        sort_by_protocol_name(&mut files);
+        for (file, protocols) in &files {
+            let protocols = protocols.as_deref().unwrap_or_default();
+            inserter.insert_file(repo, repo_id, file, protocols)?;
+        }
+    }
 
-    let prepare = |s: &'static str| {
+    Ok(inserter.invalid)
+}
+
+/// Sorts the files of a repo and their protocols by the names of the protocols.
+///
+/// The protocols of a repo are thereby inserted in the order of their names.
+fn sort_by_protocol_name(files: &mut [(&File, Option<Vec<Protocol>>)]) {
+    for (_, protocols) in &mut *files {
+        if let Some(protocols) = protocols {
+            protocols.sort_by(|p1, p2| p1.name.cmp(&p2.name));
+        }
+    }
+    let name = |p: &Option<Vec<Protocol>>| p.as_ref()?.first().map(|p| p.name.clone());
+    files.sort_by_cached_key(|(file, protocols)| (name(protocols), file.path.clone()));
+}
+
+/// Replaces the rows of the files that changed since the last build.
+///
+/// Returns the files whose protocols were skipped like [`insert`].
+fn update(tx: &Transaction<'_>, repos: &[Repo]) -> Result<Vec<String>, GeneratorError> {
+    struct OldFile {
+        file_id: i64,
+        size: i64,
+        hash: Vec<u8>,
+    }
+
+    let query = |s: &'static str| {
         tx.prepare(s)
             .map_err(|e| GeneratorError::PrepareStatement(s, e))
     };
 
     // language=sqlite
-    let mut insert_type = prepare("insert into type (type_id, name) values (?, ?)")?;
-    // language=sqlite
-    let mut insert_repo = prepare("insert into repo (repo_id, name, url) values (?, ?, ?)")?;
+    let next_id: i64 = tx
+        .query_one(
+            "select max(id) from (
+                       select max(repo_id) id from repo
+             union all select max(file_id) from file
+             union all select max(description_id) from description
+             union all select max(protocol_id) from protocol
+             union all select max(interface_id) from interface
+             union all select max(enum_id) from enum
+             union all select max(entry_id) from entry
+             union all select max(message_id) from message
+             union all select max(type_id) from type
+             union all select max(arg_id) from arg
+             union all select max(lint_rule_id) from lint_rule
+             union all select max(lint_finding_id) from lint_finding
+            )",
+            [],
+            |r| r.get::<_, Option<i64>>(0),
+        )
+        .map_err(GeneratorError::LoadExisting)?
+        .unwrap_or_default();
+    let mut inserter = Inserter {
+        tx,
+        next_id: next_id + 1,
+        types: static_map!(_ => 0),
+        rules: static_map!(_ => 0),
+        invalid: vec![],
+    };
     // language=sqlite
-    let mut insert_description =
-        prepare("insert into description (description_id, summary, body) values (?, ?, ?)")?;
+    let existing_types: HashMap<String, i64> = query("select name, type_id from type")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .and_then(|r| r.collect())
+        .map_err(GeneratorError::LoadExisting)?;
+    for ty in ArgType::variants() {
+        match existing_types.get(ty.name()) {
+            Some(&id) => inserter.types[ty] = id,
+            None => inserter.insert_type(ty)?,
+        }
+    }
     // language=sqlite
-    let mut insert_protocol = prepare(
-        "insert into protocol \
-         (protocol_id, repo_id, name, path, copyright, description_id) \
-         values \
-         (?, ?, ?, ?, ?, ?)",
-    )?;
+    let existing_rules: HashMap<String, i64> = query("select name, lint_rule_id from lint_rule")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .and_then(|r| r.collect())
+        .map_err(GeneratorError::LoadExisting)?;
+    for rule in Rule::variants() {
+        match existing_rules.get(rule.name()) {
+            Some(&id) => {
+                // language=sqlite
+                inserter
+                    .prepare(
+                        "update lint_rule set severity = ?, description = ? \
+                         where lint_rule_id = ?",
+                    )?
+                    .execute(params![rule.severity().name(), rule.description(), id])
+                    .map_err(GeneratorError::InsertLintRule)?;
+                inserter.rules[rule] = id;
+            }
+            None => inserter.insert_rule(rule)?,
+        }
+    }
     // language=sqlite
-    let mut insert_interface = prepare(
-        "insert into interface \
-         (interface_id, protocol_id, name, version, description_id) \
-         values \
-         (?, ?, ?, ?, ?)",
-    )?;
+    let mut old_repos: HashMap<String, i64> = query("select name, repo_id from repo")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .and_then(|r| r.collect())
+        .map_err(GeneratorError::LoadExisting)?;
     // language=sqlite
-    let mut insert_enum = prepare(
-        "insert into enum \
-         (enum_id, interface_id, name, since, is_bitfield, description_id) \
-         values \
-         (?, ?, ?, ?, ?, ?)",
-    )?;
+    let mut old_files: HashMap<(i64, String), OldFile> =
+        query("select repo_id, path, file_id, size, hash from file")?
+            .query_map([], |r| {
+                let file = OldFile {
+                    file_id: r.get(2)?,
+                    size: r.get(3)?,
+                    hash: r.get(4)?,
+                };
+                Ok(((r.get(0)?, r.get(1)?), file))
+            })
+            .and_then(|r| r.collect())
+            .map_err(GeneratorError::LoadExisting)?;
+
+    // These tables reference args of changed files and are recomputed by link.
     // language=sqlite
-    let mut insert_entry = prepare(
-        "insert into entry \
-         (entry_id, enum_id, name, value_str, value, summary, since, deprecated_since, description_id) \
-         values \
-         (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )?;
+    tx.execute_batch(
+        "delete from object_factory;
+         delete from rel_arg_interface;
+         delete from rel_arg_enum;",
+    )
+    .map_err(GeneratorError::DeleteStale)?;
+    let mut changed = vec![];
+    for repo in repos {
+        let repo_id = match old_repos.remove(repo.name) {
+            Some(id) => {
+                // language=sqlite
+                inserter
+                    .prepare("update repo set url = ? where repo_id = ?")?
+                    .execute(params![repo.url.trim(), id])
+                    .map_err(GeneratorError::InsertRepo)?;
+                id
+            }
+            None => inserter.insert_repo(repo)?,
+        };
+        let mut files = vec![];
+        for file in &repo.files {
+            let old = old_files.remove(&(repo_id, file.path.clone()));
+            if let Some(old) = &old
+                && old.size == file.size as i64
+                && old.hash == file.hash
+            {
+                continue;
+            }
+            if let Some(old) = old {
+                delete_file(tx, old.file_id)?;
+            }
+            files.push(file);
+        }
+        changed.push((repo, repo_id, files));
+    }
+    let files: Vec<_> = changed
+        .iter()
+        .flat_map(|(repo, _, files)| files.iter().map(move |f| (*repo, *f)))
+        .collect();
+    let mut protocols = parse_files(&files).into_iter();
+    for (repo, repo_id, files) in changed {
+        let n = files.len();
+        let mut files: Vec<_> = files.into_iter().zip(protocols.by_ref().take(n)).collect();
+        sort_by_protocol_name(&mut files);
+        for (file, protocols) in &files {
+            let protocols = protocols.as_deref().unwrap_or_default();
+            inserter.insert_file(repo, repo_id, file, protocols)?;
+        }
+    }
+    for file in old_files.into_values() {
+        delete_file(tx, file.file_id)?;
+    }
+    for repo_id in old_repos.into_values() {
+        // language=sqlite
+        tx.execute("delete from repo where repo_id = ?", [repo_id])
+            .map_err(GeneratorError::DeleteStale)?;
+    }
     // language=sqlite
-    let mut insert_message = prepare(
-        "insert into message \
-         (message_id, interface_id, number, name, is_request, is_destructor, since, deprecated_since, description_id) \
-         values \
-         (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )?;
+    tx.execute_batch(
+        "delete from description where description_id not in (
+                       select description_id from protocol where description_id is not null
+             union all select description_id from interface where description_id is not null
+             union all select description_id from enum where description_id is not null
+             union all select description_id from entry where description_id is not null
+             union all select description_id from message where description_id is not null
+             union all select description_id from arg where description_id is not null
+         )",
+    )
+    .map_err(GeneratorError::DeleteStale)?;
+
+    Ok(inserter.invalid)
+}
+
+fn delete_file(tx: &Transaction<'_>, file_id: i64) -> Result<(), GeneratorError> {
     // language=sqlite
-    let mut insert_arg = prepare(
-        "insert into arg \
-         (arg_id, message_id, position, name, type_id, summary, description_id, interface_name, allow_null, enum_name) \
-         values \
-         (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )?;
+    const STATEMENTS: &[&str] = &[
+        "delete from wire_arg where message_id in (
+             select message_id from message
+             join interface using (interface_id)
+             join protocol using (protocol_id)
+             where file_id = ?
+         )",
+        "delete from arg where message_id in (
+             select message_id from message
+             join interface using (interface_id)
+             join protocol using (protocol_id)
+             where file_id = ?
+         )",
+        "delete from message where interface_id in (
+             select interface_id from interface
+             join protocol using (protocol_id)
+             where file_id = ?
+         )",
+        "delete from entry where enum_id in (
+             select enum_id from enum
+             join interface using (interface_id)
+             join protocol using (protocol_id)
+             where file_id = ?
+         )",
+        "delete from enum where interface_id in (
+             select interface_id from interface
+             join protocol using (protocol_id)
+             where file_id = ?
+         )",
+        "delete from interface where protocol_id in (
+             select protocol_id from protocol where file_id = ?
+         )",
+        "delete from protocol where file_id = ?",
+        "delete from lint_finding where file_id = ?",
+        "delete from file where file_id = ?",
+    ];
+    for s in STATEMENTS {
+        tx.prepare_cached(s)
+            .map_err(|e| GeneratorError::PrepareStatement(s, e))?
+            .execute([file_id])
+            .map_err(GeneratorError::DeleteStale)?;
+    }
+    Ok(())
+}
+
+/// Computes `rel_arg_interface` and `rel_arg_enum` from the `arg` table and
+/// `object_factory` from the `new_id` args in `rel_arg_interface`.
+///
+/// The tables must be empty. [`update`] clears them before it deletes the args
+/// of changed files.
+///
+/// References are resolved within the protocol of the arg if possible. Otherwise
+/// they are linked to every interface or enum of that name in the database.
+fn link(tx: &Transaction<'_>) -> Result<(), GeneratorError> {
     // language=sqlite
-    let mut insert_rel_arg_interface = prepare(
-        "insert into rel_arg_interface \
-         (arg_id, interface_id) \
-         values \
-         (?, ?)",
-    )?;
+    tx.execute_batch(
+        "insert into rel_arg_interface (arg_id, interface_id)
+         select a.arg_id, t.interface_id
+         from arg a
+         join message m using (message_id)
+         join interface i using (interface_id)
+         join interface t on t.name = a.interface_name
+         where t.protocol_id = i.protocol_id
+            or not exists (
+                select 1
+                from interface l
+                where l.protocol_id = i.protocol_id
+                  and l.name = a.interface_name
+            )
+         order by a.arg_id, t.interface_id;
+
+         insert into rel_arg_enum (arg_id, enum_id)
+         with
+             arg_enum as (
+                 select
+                     a.arg_id,
+                     i.protocol_id,
+                     iif(instr(a.enum_name, '.'),
+                         substr(a.enum_name, 1, instr(a.enum_name, '.') - 1),
+                         i.name) interface_name,
+                     iif(instr(a.enum_name, '.'),
+                         substr(a.enum_name, instr(a.enum_name, '.') + 1),
+                         a.enum_name) enum_name
+                 from arg a
+                 join message m using (message_id)
+                 join interface i using (interface_id)
+                 where a.enum_name is not null
+             ),
+             candidate as (
+                 select ae.arg_id, e.enum_id, t.protocol_id = ae.protocol_id is_local
+                 from arg_enum ae
+                 join interface t on t.name = ae.interface_name
+                 join enum e on e.interface_id = t.interface_id and e.name = ae.enum_name
+             )
+         select c.arg_id, c.enum_id
+         from candidate c
+         where c.is_local
+            or not exists (
+                select 1
+                from candidate l
+                where l.arg_id = c.arg_id
+                  and l.is_local
+            )
+         order by c.arg_id, c.enum_id;
+
+         insert into object_factory
+             (parent_interface_id, message_id, arg_id, child_interface_id, since)
+         select m.interface_id, m.message_id, a.arg_id, rai.interface_id, coalesce(m.since, 1)
+         from rel_arg_interface rai
+         join arg a using (arg_id)
+         join type t using (type_id)
+         join message m using (message_id)
+         where t.name = 'new_id'
+         order by a.arg_id, rai.interface_id;",
+    )
+    .map_err(GeneratorError::Link)
+}
+
+/// Recomputes `interface.is_global` from `object_factory` and applies the
+/// overrides of the repos.
+///
+/// The reason of interfaces that are not globals names a message that creates
+/// them, preferring messages in the same protocol.
+fn classify_globals(tx: &Transaction<'_>, repos: &[Repo]) -> Result<(), GeneratorError> {
     // language=sqlite
-    let mut insert_rel_arg_enum = prepare(
-        "insert into rel_arg_enum \
-         (arg_id, enum_id) \
-         values \
-         (?, ?)",
-    )?;
-
-    let mut insert_description = |id: i64, description: &Description| {
-        insert_description
-            .execute(params![
-                id,
-                &description.summary,
-                format_ml_text(&description.body)
-            ])
-            .map_err(GeneratorError::InsertDescription)
-    };
-    macro_rules! insert_description {
-        ($description:expr) => {{
-            let mut description_id = None;
-            if let Some(description) = $description {
-                let id = next_id();
-                description_id = Some(id);
-                insert_description(id, description)?;
+    tx.execute_batch(
+        "update interface
+         set is_global = not exists (
+                 select 1
+                 from object_factory f
+                 where f.child_interface_id = interface.interface_id
+             ),
+             global_reason = coalesce(
+                 (
+                     select 'created by ' || i.name || '.' || m.name
+                     from object_factory f
+                     join message m using (message_id)
+                     join interface i on i.interface_id = f.parent_interface_id
+                     join protocol p on p.protocol_id = i.protocol_id
+                     join repo r using (repo_id)
+                     join interface c on c.interface_id = f.child_interface_id
+                     where c.interface_id = interface.interface_id
+                     order by p.protocol_id != c.protocol_id,
+                              r.name, p.name, i.name, m.is_request desc, m.number
+                     limit 1
+                 ),
+                 'not created by any new_id arg'
+             );",
+    )
+    .map_err(GeneratorError::ClassifyGlobals)?;
+    for repo in repos {
+        for global in repo.globals {
+            // language=sqlite
+            let sql = "
+                update interface
+                set is_global = ?, global_reason = ?
+                where name = ?
+                  and protocol_id in (
+                      select protocol_id
+                      from protocol
+                      join repo using (repo_id)
+                      where repo.name = ?
+                  )
+            ";
+            let changed = tx
+                .prepare_cached(sql)
+                .map_err(|e| GeneratorError::PrepareStatement(sql, e))?
+                .execute(params![
+                    global.is_global,
+                    global.reason,
+                    global.interface,
+                    repo.name,
+                ])
+                .map_err(GeneratorError::ClassifyGlobals)?;
+            if changed == 0 {
+                eprintln!(
+                    "The global override for {}/{} matches no interface",
+                    repo.name, global.interface,
+                );
             }
-            description_id
-        }};
-    }
-
-    let types: StaticMap<ArgType, _> = static_map! {
-        ty => {
-            let id = next_id();
-            let name = match ty {
-                ArgType::NewId => "new_id",
-                ArgType::Int => "int",
-                ArgType::Uint => "uint",
-                ArgType::Fixed => "fixed",
-                ArgType::String => "string",
-                ArgType::Object => "object",
-                ArgType::Array => "array",
-                ArgType::Fd => "fd",
-            };
-            insert_type
-                .execute(params![id, name])
-                .map_err(GeneratorError::InsertType)?;
-            id
         }
-    };
+    }
+    Ok(())
+}
+
+struct Inserter<'a> {
+    tx: &'a Transaction<'a>,
+    next_id: i64,
+    types: StaticMap<ArgType, i64>,
+    rules: StaticMap<Rule, i64>,
+    /// The files whose protocols violate constraints of the schema, with the
+    /// errors.
+    invalid: Vec<String>,
+}
+
+impl<'a> Inserter<'a> {
+    fn next_id(&mut self) -> i64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn prepare(&self, s: &'static str) -> Result<CachedStatement<'a>, GeneratorError> {
+        self.tx
+            .prepare_cached(s)
+            .map_err(|e| GeneratorError::PrepareStatement(s, e))
+    }
+
+    fn insert_types(&mut self) -> Result<(), GeneratorError> {
+        for ty in ArgType::variants() {
+            self.insert_type(ty)?;
+        }
+        Ok(())
+    }
+
+    fn insert_type(&mut self, ty: ArgType) -> Result<(), GeneratorError> {
+        let id = self.next_id();
+        // language=sqlite
+        self.prepare("insert into type (type_id, name) values (?, ?)")?
+            .execute(params![id, ty.name()])
+            .map_err(GeneratorError::InsertType)?;
+        self.types[ty] = id;
+        Ok(())
+    }
 
-    let mut interface_deps: IndexMap<&str, InterfaceDep<'_>> = Default::default();
-    #[derive(Default, Debug)]
-    struct InterfaceDep<'a> {
-        interface_ids: Vec<i64>,
-        enums: IndexMap<&'a str, EnumDep>,
-        arg_ids: Vec<i64>,
+    fn insert_rules(&mut self) -> Result<(), GeneratorError> {
+        for rule in Rule::variants() {
+            self.insert_rule(rule)?;
+        }
+        Ok(())
     }
-    #[derive(Default, Debug)]
-    struct EnumDep {
-        enum_ids: Vec<i64>,
-        arg_ids: Vec<i64>,
+
+    fn insert_rule(&mut self, rule: Rule) -> Result<(), GeneratorError> {
+        let id = self.next_id();
+        // language=sqlite
+        self.prepare(
+            "insert into lint_rule (lint_rule_id, name, severity, description) \
+             values (?, ?, ?, ?)",
+        )?
+        .execute(params![
+            id,
+            rule.name(),
+            rule.severity().name(),
+            rule.description()
+        ])
+        .map_err(GeneratorError::InsertLintRule)?;
+        self.rules[rule] = id;
+        Ok(())
     }
 
-    for repo in &repos {
-        let repo_id = next_id();
-        insert_repo
+    fn insert_repo(&mut self, repo: &Repo) -> Result<i64, GeneratorError> {
+        let repo_id = self.next_id();
+        // language=sqlite
+        self.prepare("insert into repo (repo_id, name, url) values (?, ?, ?)")?
             .execute(params![repo_id, &repo.name, repo.url.trim()])
             .map_err(GeneratorError::InsertRepo)?;
-        for protocol in &repo.protocols {
-            struct LocalInterface<'a> {
-                interface_id: i64,
-                interface: &'a Interface,
-                enums: HashMap<&'a str, i64>,
-            }
-            let mut interfaces: IndexMap<&str, LocalInterface<'_>> = Default::default();
-            let protocol_id = next_id();
-            let description_id = insert_description!(&protocol.description);
-            insert_protocol
-                .execute(params![
-                    protocol_id,
-                    repo_id,
-                    &protocol.name,
-                    &protocol.path,
-                    protocol.copyright.as_ref().map(|c| format_ml_text(&c.body)),
-                    description_id,
-                ])
-                .map_err(GeneratorError::InsertProtocol)?;
-            for interface in &protocol.interfaces {
-                let interface_id = next_id();
-                let interface_dep = interface_deps.entry(&interface.name).or_default();
-                interface_dep.interface_ids.push(interface_id);
-                let local_interface = interfaces
-                    .entry(&interface.name)
-                    .insert_entry(LocalInterface {
-                        interface_id,
-                        interface,
-                        enums: Default::default(),
-                    })
-                    .into_mut();
-                let description_id = insert_description!(&interface.description);
-                insert_interface
-                    .execute(params![
-                        interface_id,
-                        protocol_id,
-                        &interface.name,
-                        interface.version as i64,
-                        description_id,
-                    ])
-                    .map_err(GeneratorError::InsertInterface)?;
-                for enum_ in &interface.enums {
-                    let enum_id = next_id();
-                    interface_dep
-                        .enums
-                        .entry(&enum_.name)
-                        .or_default()
-                        .enum_ids
-                        .push(enum_id);
-                    local_interface.enums.insert(&enum_.name, enum_id);
-                    let description_id = insert_description!(&enum_.description);
-                    insert_enum
-                        .execute(params![
-                            enum_id,
-                            interface_id,
-                            &enum_.name,
-                            enum_.since.map(|v| v as i64),
-                            enum_.bitfield,
-                            description_id,
-                        ])
-                        .map_err(GeneratorError::InsertEnum)?;
-                    for entry in &enum_.entries {
-                        let entry_id = next_id();
-                        let description_id = insert_description!(&entry.description);
-                        insert_entry
-                            .execute(params![
-                                entry_id,
-                                enum_id,
-                                &entry.name,
-                                &entry.value,
-                                entry.value_i64,
-                                &entry.summary,
-                                entry.since,
-                                entry.deprecated_since,
-                                description_id,
-                            ])
-                            .map_err(GeneratorError::InsertEntry)?;
-                    }
-                }
-            }
-            for interface in interfaces.values() {
-                for message in &interface.interface.messages {
-                    let message_id = next_id();
-                    let description_id = insert_description!(&message.description);
-                    insert_message
-                        .execute(params![
-                            message_id,
-                            interface.interface_id,
-                            message.message_id as i64,
-                            &message.name,
-                            message.is_request,
-                            message.ty == Some(MessageType::Destructor),
-                            message.since,
-                            message.deprecated_since,
-                            description_id,
-                        ])
-                        .map_err(GeneratorError::InsertMessage)?;
-                    for (pos, arg) in message.args.iter().enumerate() {
-                        let arg_id = next_id();
-                        let description_id = insert_description!(&arg.description);
-                        insert_arg
-                            .execute(params![
-                                arg_id,
-                                message_id,
-                                pos as i64,
-                                &arg.name,
-                                types[arg.ty],
-                                &arg.summary,
-                                description_id,
-                                &arg.interface,
-                                arg.allow_null,
-                                &arg.enum_,
-                            ])
-                            .map_err(GeneratorError::InsertArg)?;
-                        if let Some(interface_name) = &arg.interface {
-                            if let Some(local_interface) = interfaces.get(&**interface_name) {
-                                insert_rel_arg_interface
-                                    .insert(params![arg_id, local_interface.interface_id])
-                                    .map_err(GeneratorError::InsertRelArgInterface)?;
-                            } else {
-                                interface_deps
-                                    .entry(interface_name)
-                                    .or_default()
-                                    .arg_ids
-                                    .push(arg_id);
-                            }
-                        }
-                        if let Some(enum_name) = &arg.enum_ {
-                            let (interface_name, enum_name) = enum_name
-                                .split_once(".")
-                                .unwrap_or((&interface.interface.name, enum_name));
-                            if let Some(local_interface) = interfaces.get(interface_name)
-                                && let Some(enum_id) = local_interface.enums.get(enum_name)
-                            {
-                                insert_rel_arg_enum
-                                    .insert(params![arg_id, enum_id])
-                                    .map_err(GeneratorError::InsertRelArgEnum)?;
-                            } else {
-                                interface_deps
-                                    .entry(interface_name)
-                                    .or_default()
-                                    .enums
-                                    .entry(enum_name)
-                                    .or_default()
-                                    .arg_ids
-                                    .push(arg_id);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        Ok(repo_id)
     }
 
-    for dep in interface_deps.values() {
-        for &interface_id in &dep.interface_ids {
-            for &arg_id in &dep.arg_ids {
-                insert_rel_arg_interface
-                    .insert(params![arg_id, interface_id])
-                    .map_err(GeneratorError::InsertRelArgInterface)?;
-            }
+    fn insert_description(
+        &mut self,
+        description: &Option<Description>,
+    ) -> Result<Option<i64>, GeneratorError> {
+        let Some(description) = description else {
+            return Ok(None);
+        };
+        let id = self.next_id();
+        // language=sqlite
+        self.prepare("insert into description (description_id, summary, body) values (?, ?, ?)")?
+            .execute(params![
+                id,
+                &description.summary,
+                format_ml_text(&description.body)
+            ])
+            .map_err(GeneratorError::InsertDescription)?;
+        Ok(Some(id))
+    }
+
+    /// Inserts a file and its protocols.
+    ///
+    /// If the protocols violate a constraint of the schema, the file is still
+    /// inserted but its protocols are skipped and the file is added to
+    /// `invalid`.
+    fn insert_file(
+        &mut self,
+        repo: &Repo,
+        repo_id: i64,
+        file: &File,
+        protocols: &[Protocol],
+    ) -> Result<(), GeneratorError> {
+        let file_id = self.next_id();
+        // language=sqlite
+        self.prepare(
+            "insert into file \
+             (file_id, repo_id, path, size, hash) \
+             values \
+             (?, ?, ?, ?, ?)",
+        )?
+        .execute(params![
+            file_id,
+            repo_id,
+            &file.path,
+            file.size as i64,
+            &file.hash[..],
+        ])
+        .map_err(GeneratorError::InsertFile)?;
+        for finding in lint(protocols) {
+            let id = self.next_id();
+            // language=sqlite
+            self.prepare(
+                "insert into lint_finding \
+                 (lint_finding_id, lint_rule_id, file_id, location, message) \
+                 values \
+                 (?, ?, ?, ?, ?)",
+            )?
+            .execute(params![
+                id,
+                self.rules[finding.rule],
+                file_id,
+                &finding.location,
+                &finding.message,
+            ])
+            .map_err(GeneratorError::InsertLintFinding)?;
         }
-        for enum_ in dep.enums.values() {
-            for &enum_id in &enum_.enum_ids {
-                for &arg_id in &enum_.arg_ids {
-                    insert_rel_arg_enum
-                        .insert(params![arg_id, enum_id])
-                        .map_err(GeneratorError::InsertRelArgEnum)?;
-                }
+        // language=sqlite
+        self.tx
+            .execute_batch("savepoint protocols")
+            .map_err(GeneratorError::Savepoint)?;
+        let res = protocols
+            .iter()
+            .try_for_each(|protocol| self.insert_protocol(repo_id, file_id, protocol));
+        if let Err(e) = res {
+            if !e.is_constraint_violation() {
+                return Err(e);
             }
+            self.invalid
+                .push(format!("{}/{}: {}", repo.name, file.path, Report::new(e),));
+            // language=sqlite
+            self.tx
+                .execute_batch("rollback to protocols")
+                .map_err(GeneratorError::Savepoint)?;
         }
+        // language=sqlite
+        self.tx
+            .execute_batch("release protocols")
+            .map_err(GeneratorError::Savepoint)
     }
 
-    Ok(())
-}
+    fn insert_protocol(
+        &mut self,
+        repo_id: i64,
+        file_id: i64,
+        protocol: &Protocol,
+    ) -> Result<(), GeneratorError> {
+        let protocol_id = self.next_id();
+        let description_id = self.insert_description(&protocol.description)?;
+        // language=sqlite
+        self.prepare(
+            "insert into protocol \
+             (protocol_id, repo_id, file_id, name, path, copyright, description_id) \
+             values \
+             (?, ?, ?, ?, ?, ?, ?)",
+        )?
+        .execute(params![
+            protocol_id,
+            repo_id,
+            file_id,
+            &protocol.name,
+            &protocol.path,
+            protocol.copyright.as_ref().map(|c| format_ml_text(&c.body)),
+            description_id,
+        ])
+        .map_err(GeneratorError::InsertProtocol)?;
+        for interface in &protocol.interfaces {
+            self.insert_interface(protocol_id, interface)?;
+        }
+        Ok(())
+    }
 
-fn format_ml_text(description: &str) -> String {
-    let mut trim = None;
-    let mut empty_lines = 0;
-    let mut out = String::new();
-    'outer: for mut line in description.lines() {
-        if trim.is_none() {
-            let mut spaces = 0usize;
-            'spaces: {
-                for c in line.chars() {
-                    if c == ' ' {
-                        spaces += 1;
-                    } else if c == '\t' {
-                        spaces = (spaces + 8) & !7;
-                    } else {
-                        break 'spaces;
-                    }
-                }
-                continue 'outer;
-            }
-            trim = Some(spaces);
+    fn insert_interface(
+        &mut self,
+        protocol_id: i64,
+        interface: &Interface,
+    ) -> Result<(), GeneratorError> {
+        let interface_id = self.next_id();
+        let description_id = self.insert_description(&interface.description)?;
+        // language=sqlite
+        self.prepare(
+            "insert into interface \
+             (interface_id, protocol_id, name, version, description_id) \
+             values \
+             (?, ?, ?, ?, ?)",
+        )?
+        .execute(params![
+            interface_id,
+            protocol_id,
+            &interface.name,
+            interface.version as i64,
+            description_id,
+        ])
+        .map_err(GeneratorError::InsertInterface)?;
+        for enum_ in &interface.enums {
+            self.insert_enum(interface_id, enum_)?;
         }
-        let trim = trim.unwrap();
-        let mut line_buf = String::new();
-        if line.contains('\t') {
-            let mut offset = 0;
-            for c in line.chars() {
-                if c == '\t' {
-                    line_buf.push(' ');
-                    offset += 1;
-                    let delta = (-offset) & 7;
-                    for _ in 0..delta {
-                        line_buf.push(' ');
-                    }
-                    offset += delta;
-                } else {
-                    line_buf.push(c);
-                    offset += 1;
-                }
-            }
-            line = &line_buf;
+        for message in &interface.messages {
+            self.insert_message(interface_id, message)?;
         }
-        let idx = 'idx: {
-            let mut spaces = 0usize;
-            for (idx, c) in line.char_indices() {
-                if c == ' ' {
-                    spaces += 1;
-                } else {
-                    break 'idx idx;
-                }
-                if spaces >= trim {
-                    break 'idx idx + 1;
-                }
-            }
-            line.len()
-        };
-        line = &line[idx..];
-        if line.trim_ascii().is_empty() {
-            empty_lines += 1;
-            continue;
+        Ok(())
+    }
+
+    fn insert_enum(&mut self, interface_id: i64, enum_: &Enum) -> Result<(), GeneratorError> {
+        let enum_id = self.next_id();
+        let description_id = self.insert_description(&enum_.description)?;
+        // language=sqlite
+        self.prepare(
+            "insert into enum \
+             (enum_id, interface_id, name, since, is_bitfield, description_id) \
+             values \
+             (?, ?, ?, ?, ?, ?)",
+        )?
+        .execute(params![
+            enum_id,
+            interface_id,
+            &enum_.name,
+            enum_.since.map(|v| v as i64),
+            enum_.bitfield,
+            description_id,
+        ])
+        .map_err(GeneratorError::InsertEnum)?;
+        for entry in &enum_.entries {
+            let entry_id = self.next_id();
+            let description_id = self.insert_description(&entry.description)?;
+            // language=sqlite
+            self.prepare(
+                "insert into entry \
+                 (entry_id, enum_id, name, value_str, value, summary, since, deprecated_since, description_id) \
+                 values \
+                 (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )?
+            .execute(params![
+                entry_id,
+                enum_id,
+                &entry.name,
+                &entry.value,
+                entry.value_i64,
+                &entry.summary,
+                entry.since,
+                entry.deprecated_since,
+                description_id,
+            ])
+            .map_err(GeneratorError::InsertEntry)?;
         }
-        if empty_lines > 0 {
-            for _ in 0..empty_lines {
-                out.push_str("\n");
-            }
-            empty_lines = 0;
+        Ok(())
+    }
+
+    fn insert_message(
+        &mut self,
+        interface_id: i64,
+        message: &Message,
+    ) -> Result<(), GeneratorError> {
+        let message_id = self.next_id();
+        let description_id = self.insert_description(&message.description)?;
+        // language=sqlite
+        self.prepare(
+            "insert into message \
+             (message_id, interface_id, number, name, is_request, is_destructor, since, deprecated_since, description_id, signature) \
+             values \
+             (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )?
+        .execute(params![
+            message_id,
+            interface_id,
+            message.message_id as i64,
+            &message.name,
+            message.is_request,
+            message.ty == Some(MessageType::Destructor),
+            message.since,
+            message.deprecated_since,
+            description_id,
+            message.signature(),
+        ])
+        .map_err(GeneratorError::InsertMessage)?;
+        let mut arg_ids = vec![];
+        for (pos, arg) in message.args.iter().enumerate() {
+            let arg_id = self.next_id();
+            let description_id = self.insert_description(&arg.description)?;
+            // language=sqlite
+            self.prepare(
+                "insert into arg \
+                 (arg_id, message_id, position, name, type_id, summary, description_id, interface_name, allow_null, enum_name) \
+                 values \
+                 (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )?
+            .execute(params![
+                arg_id,
+                message_id,
+                pos as i64,
+                &arg.name,
+                self.types[arg.ty],
+                &arg.summary,
+                description_id,
+                &arg.interface,
+                arg.allow_null,
+                &arg.enum_,
+            ])
+            .map_err(GeneratorError::InsertArg)?;
+            arg_ids.push(arg_id);
+        }
+        for (pos, arg) in message.wire_args().iter().enumerate() {
+            // language=sqlite
+            self.prepare(
+                "insert into wire_arg \
+                 (message_id, position, arg_id, type_id, allow_null) \
+                 values \
+                 (?, ?, ?, ?, ?)",
+            )?
+            .execute(params![
+                message_id,
+                pos as i64,
+                arg_ids[arg.arg],
+                self.types[arg.ty],
+                arg.allow_null,
+            ])
+            .map_err(GeneratorError::InsertWireArg)?;
         }
-        out.push_str(line);
-        out.push_str("\n");
+        Ok(())
     }
-    out
 }
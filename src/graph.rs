@@ -0,0 +1,390 @@
+use {
+    clap::{Args, ValueEnum},
+    std::{
+        collections::{BTreeSet, HashMap, HashSet, VecDeque},
+        fmt::Write as _,
+        fs,
+        io::{self, Write},
+        path::PathBuf,
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::ArgType,
+        db::{Database, DbError, InterfaceRef, Model},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("there is no repo {0}")]
+    UnknownRepo(String),
+    #[error("there is no interface {0}")]
+    UnknownInterface(String),
+    #[error("interface {0} exists in the repos {1}, use REPO/INTERFACE to select one")]
+    Ambiguous(String, String),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("could not write to stdout")]
+    WriteStdout(#[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    /// The output format.
+    #[clap(long, value_enum, default_value_t = Format::Dot)]
+    format: Format,
+    /// Only include edges that start in this repo.
+    #[clap(long)]
+    repo: Option<String>,
+    /// Only include what can be reached from this interface, given as
+    /// INTERFACE or REPO/INTERFACE.
+    #[clap(long)]
+    root: Option<String>,
+    /// The database to read the protocols from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The output file (default: stdout).
+    #[clap(long)]
+    output: Option<PathBuf>,
+    /// The graph to write.
+    kind: Kind,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Format {
+    /// Graphviz.
+    Dot,
+    /// Mermaid flowchart.
+    Mermaid,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Kind {
+    /// The protocols whose interfaces or enums are referenced by each protocol.
+    Protocols,
+    /// The interfaces referenced by the args of each interface.
+    Interfaces,
+    /// The interfaces created by the `new_id` args of each interface.
+    Objects,
+}
+
+pub fn main(args: GraphArgs) -> Result<(), GraphError> {
+    let db = Database::open(&args.db)?;
+    let model = db.load()?;
+    if let Some(repo) = &args.repo
+        && !model.repos.iter().any(|r| r.name == *repo)
+    {
+        return Err(GraphError::UnknownRepo(repo.clone()));
+    }
+    let mut graph = match args.kind {
+        Kind::Protocols => Graph::protocols(&model),
+        Kind::Interfaces => Graph::interfaces(&model, &db.globals()?),
+        Kind::Objects => Graph::objects(&model, &db.globals()?),
+    };
+    if let Some(repo) = &args.repo {
+        graph.retain_repo(repo);
+    }
+    if let Some(root) = &args.root {
+        let root = find_root(&model, root)?;
+        graph.retain_reachable(args.kind, root);
+    }
+    let out = match args.format {
+        Format::Dot => graph.dot(),
+        Format::Mermaid => graph.mermaid(),
+    };
+    match &args.output {
+        Some(path) => fs::write(path, out).map_err(|e| GraphError::Write(path.clone(), e)),
+        None => io::stdout()
+            .lock()
+            .write_all(out.as_bytes())
+            .map_err(GraphError::WriteStdout),
+    }
+}
+
+/// Looks up an interface given as `INTERFACE` or `REPO/INTERFACE` and returns
+/// its repo, protocol, and name.
+fn find_root<'a>(model: &'a Model, name: &str) -> Result<(&'a str, &'a str, &'a str), GraphError> {
+    let (repo, interface) = match name.split_once('/') {
+        Some((repo, interface)) => (Some(repo), interface),
+        None => (None, name),
+    };
+    let mut matches = vec![];
+    for r in &model.repos {
+        if repo.is_some_and(|repo| repo != r.name) {
+            continue;
+        }
+        for p in &r.protocols {
+            for i in &p.interfaces {
+                if i.name == interface {
+                    matches.push((&*r.name, &*p.name, &*i.name));
+                }
+            }
+        }
+    }
+    if matches.len() > 1 {
+        let repos: Vec<_> = matches.iter().map(|m| m.0).collect();
+        return Err(GraphError::Ambiguous(name.to_string(), repos.join(", ")));
+    }
+    matches
+        .pop()
+        .ok_or_else(|| GraphError::UnknownInterface(name.to_string()))
+}
+
+struct Node {
+    repo: String,
+    protocol: String,
+    /// The interface or `None` for protocol nodes.
+    interface: Option<String>,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    /// The included nodes. Nodes that are not included are not written.
+    included: BTreeSet<usize>,
+    /// The edges with their labels.
+    edges: BTreeSet<(usize, usize, String)>,
+}
+
+impl Graph {
+    fn protocols(model: &Model) -> Self {
+        let mut graph = Self::default();
+        let mut ids = HashMap::new();
+        for repo in &model.repos {
+            for protocol in &repo.protocols {
+                let id = graph.add(&repo.name, &protocol.name, None);
+                ids.insert((&*repo.name, &*protocol.name), id);
+            }
+        }
+        let interface_targets = model
+            .arg_interfaces
+            .iter()
+            .flat_map(|(path, t)| t.iter().map(move |t| (path, &t.repo, &t.protocol)));
+        let enum_targets = model
+            .arg_enums
+            .iter()
+            .flat_map(|(path, t)| t.iter().map(move |t| (path, &t.repo, &t.protocol)));
+        for (path, repo, protocol) in interface_targets.chain(enum_targets) {
+            let from = ids[&(&*path.repo, &*path.protocol)];
+            let to = ids[&(&**repo, &**protocol)];
+            if from != to {
+                graph.edges.insert((from, to, String::new()));
+            }
+        }
+        graph
+    }
+
+    fn interfaces(model: &Model, globals: &[InterfaceRef]) -> Self {
+        let mut graph = Self::interface_graph(model, |_| true, |_| String::new());
+        graph.add_binds(globals, "");
+        graph
+    }
+
+    fn objects(model: &Model, globals: &[InterfaceRef]) -> Self {
+        let mut graph = Self::interface_graph(
+            model,
+            |ty| ty == ArgType::NewId,
+            |message| message.to_string(),
+        );
+        graph.add_binds(globals, "bind");
+        graph
+    }
+
+    /// Creates a graph of the interfaces with an edge for every arg whose type
+    /// matches `filter` and whose `interface` attribute resolves to an
+    /// interface.
+    fn interface_graph(
+        model: &Model,
+        filter: impl Fn(ArgType) -> bool,
+        label: impl Fn(&str) -> String,
+    ) -> Self {
+        let mut graph = Self::default();
+        let mut ids = HashMap::new();
+        for repo in &model.repos {
+            for protocol in &repo.protocols {
+                for interface in &protocol.interfaces {
+                    let id = graph.add(&repo.name, &protocol.name, Some(&interface.name));
+                    ids.insert((&*repo.name, &*protocol.name, &*interface.name), id);
+                }
+            }
+        }
+        for repo in &model.repos {
+            for protocol in &repo.protocols {
+                for interface in &protocol.interfaces {
+                    let from = ids[&(&*repo.name, &*protocol.name, &*interface.name)];
+                    for message in &interface.messages {
+                        for arg in message.args.iter().filter(|a| filter(a.ty)) {
+                            let path = Model::arg_path(repo, protocol, interface, message, arg);
+                            let targets = model.arg_interfaces.get(&path).map(|t| &t[..]);
+                            for target in targets.unwrap_or_default() {
+                                let key = (&*target.repo, &*target.protocol, &*target.interface);
+                                graph.edges.insert((from, ids[&key], label(&message.name)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Adds an edge from `wl_registry` to every global.
+    ///
+    /// The `new_id` arg of `wl_registry.bind` has no `interface` attribute, so
+    /// these edges are not derived from the args.
+    fn add_binds(&mut self, globals: &[InterfaceRef], label: &str) {
+        let mut ids = HashMap::new();
+        let mut registries = vec![];
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if let Some(interface) = &node.interface {
+                ids.insert((&*node.repo, &*node.protocol, &**interface), idx);
+                if interface == "wl_registry" {
+                    registries.push(idx);
+                }
+            }
+        }
+        for global in globals {
+            let key = (&*global.repo, &*global.protocol, &*global.interface);
+            let Some(&to) = ids.get(&key) else {
+                continue;
+            };
+            for &from in &registries {
+                self.edges.insert((from, to, label.to_string()));
+            }
+        }
+    }
+
+    fn add(&mut self, repo: &str, protocol: &str, interface: Option<&str>) -> usize {
+        self.included.insert(self.nodes.len());
+        self.nodes.push(Node {
+            repo: repo.to_string(),
+            protocol: protocol.to_string(),
+            interface: interface.map(|i| i.to_string()),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Removes the edges that do not start in the repo and the nodes that are
+    /// neither in the repo nor the target of an edge.
+    fn retain_repo(&mut self, repo: &str) {
+        let nodes = &self.nodes;
+        self.edges.retain(|e| nodes[e.0].repo == repo);
+        let targets: HashSet<_> = self.edges.iter().map(|e| e.1).collect();
+        self.included
+            .retain(|&n| nodes[n].repo == repo || targets.contains(&n));
+    }
+
+    /// Removes the nodes and edges that cannot be reached from the root
+    /// interface or, for protocol graphs, from the protocol of the root.
+    fn retain_reachable(&mut self, kind: Kind, (repo, protocol, interface): (&str, &str, &str)) {
+        let mut queue = VecDeque::new();
+        for &idx in &self.included {
+            let node = &self.nodes[idx];
+            let is_root = node.repo == repo
+                && node.protocol == protocol
+                && match kind {
+                    Kind::Protocols => true,
+                    Kind::Interfaces | Kind::Objects => {
+                        node.interface.as_deref() == Some(interface)
+                    }
+                };
+            if is_root {
+                queue.push_back(idx);
+            }
+        }
+        let mut reached: BTreeSet<_> = queue.iter().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            for edge in self.edges.iter().filter(|e| e.0 == node) {
+                if reached.insert(edge.1) {
+                    queue.push_back(edge.1);
+                }
+            }
+        }
+        self.edges.retain(|e| reached.contains(&e.0));
+        self.included = reached;
+    }
+
+    /// Returns the included nodes grouped by repo for protocol nodes or by
+    /// protocol for interface nodes.
+    fn clusters(&self) -> Vec<(String, Vec<usize>)> {
+        let mut res: Vec<(String, Vec<usize>)> = vec![];
+        for &idx in &self.included {
+            let node = &self.nodes[idx];
+            let cluster = match node.interface {
+                Some(_) => format!("{}/{}", node.repo, node.protocol),
+                None => node.repo.clone(),
+            };
+            match res.last_mut() {
+                Some((name, nodes)) if *name == cluster => nodes.push(idx),
+                _ => res.push((cluster, vec![idx])),
+            }
+        }
+        res
+    }
+
+    fn label(&self, node: usize) -> &str {
+        let node = &self.nodes[node];
+        node.interface.as_deref().unwrap_or(&node.protocol)
+    }
+
+    fn dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("digraph wayland {\n");
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [shape=box];\n");
+        for (idx, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let _ = writeln!(out, "    subgraph cluster_{idx} {{");
+            let _ = writeln!(out, "        label=\"{}\";", dot_escape(cluster));
+            for &node in nodes {
+                let _ = writeln!(
+                    out,
+                    "        n{node} [label=\"{}\"];",
+                    dot_escape(self.label(node))
+                );
+            }
+            out.push_str("    }\n");
+        }
+        for (from, to, label) in &self.edges {
+            let _ = write!(out, "    n{from} -> n{to}");
+            if !label.is_empty() {
+                let _ = write!(out, " [label=\"{}\"]", dot_escape(label));
+            }
+            out.push_str(";\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn mermaid(&self) -> String {
+        let mut out = String::new();
+        out.push_str("flowchart LR\n");
+        for (idx, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let _ = writeln!(out, "    subgraph c{idx} [\"{}\"]", mermaid_escape(cluster));
+            for &node in nodes {
+                let _ = writeln!(
+                    out,
+                    "        n{node}[\"{}\"]",
+                    mermaid_escape(self.label(node))
+                );
+            }
+            out.push_str("    end\n");
+        }
+        for (from, to, label) in &self.edges {
+            let _ = match label.is_empty() {
+                true => writeln!(out, "    n{from} --> n{to}"),
+                false => writeln!(out, "    n{from} -->|\"{}\"| n{to}", mermaid_escape(label)),
+            };
+        }
+        out
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
@@ -0,0 +1,14 @@
+//! A parser for Wayland protocol XML files and typed access to `wayland.db`.
+//!
+//! [`parser::parse`] converts the contents of a protocol file into the types in
+//! [`ast`]. These types implement `serde::Serialize` and `serde::Deserialize`.
+//! [`writer::write`] converts them back to canonical XML.
+//!
+//! The `wayland-db` binary in this crate uses this parser to build `wayland.db`.
+//! [`db::Database`] reads the database back into the same types.
+
+pub mod ast;
+pub mod db;
+pub mod parser;
+pub mod text;
+pub mod writer;
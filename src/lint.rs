@@ -0,0 +1,350 @@
+use {
+    crate::collector::{collect, find_xml_files, parse_files},
+    clap::Args,
+    error_reporter::Report,
+    linearize::{Linearize, LinearizeExt},
+    std::{collections::HashMap, fs, io, path::PathBuf},
+    thiserror::Error,
+    wayland_db::{
+        ast::{ArgType, Description, Enum, Interface, Message, Protocol},
+        parser::parse,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum LintError {
+    #[error("could not search for protocol files")]
+    Walk(#[source] walkdir::Error),
+    #[error("could not read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("found {0} errors")]
+    Errors(usize),
+    #[error("{0} files could not be parsed")]
+    Parse(usize),
+}
+
+#[derive(Args, Debug)]
+pub struct LintArgs {
+    /// Print the rules and exit.
+    #[clap(long)]
+    list_rules: bool,
+    /// The protocol files to check. Directories are searched for `.xml` files.
+    /// Without paths, the files of all repositories are checked.
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Linearize)]
+pub(crate) enum Rule {
+    SinceExceedsVersion,
+    DeprecatedBeforeSince,
+    DuplicateEntryValue,
+    BitfieldNotPowerOfTwo,
+    EnumOnNonInteger,
+    NewIdWithoutInterface,
+    MissingDescription,
+    MissingSummary,
+}
+
+impl Rule {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Rule::SinceExceedsVersion => "since-exceeds-version",
+            Rule::DeprecatedBeforeSince => "deprecated-before-since",
+            Rule::DuplicateEntryValue => "duplicate-entry-value",
+            Rule::BitfieldNotPowerOfTwo => "bitfield-not-power-of-two",
+            Rule::EnumOnNonInteger => "enum-on-non-integer",
+            Rule::NewIdWithoutInterface => "new-id-without-interface",
+            Rule::MissingDescription => "missing-description",
+            Rule::MissingSummary => "missing-summary",
+        }
+    }
+
+    pub(crate) fn severity(self) -> Severity {
+        match self {
+            Rule::SinceExceedsVersion => Severity::Error,
+            Rule::DeprecatedBeforeSince => Severity::Error,
+            Rule::DuplicateEntryValue => Severity::Warning,
+            Rule::BitfieldNotPowerOfTwo => Severity::Warning,
+            Rule::EnumOnNonInteger => Severity::Error,
+            Rule::NewIdWithoutInterface => Severity::Warning,
+            Rule::MissingDescription => Severity::Info,
+            Rule::MissingSummary => Severity::Info,
+        }
+    }
+
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Rule::SinceExceedsVersion => {
+                "The since or deprecated-since attribute of a message, enum or entry is \
+                 greater than the version of its interface."
+            }
+            Rule::DeprecatedBeforeSince => {
+                "The deprecated-since attribute of a message or entry is not greater than \
+                 its since attribute."
+            }
+            Rule::DuplicateEntryValue => "An entry has the same value as a previous entry.",
+            Rule::BitfieldNotPowerOfTwo => {
+                "An entry of a bitfield enum is neither 0 nor a power of two."
+            }
+            Rule::EnumOnNonInteger => "An arg with an enum attribute is neither int nor uint.",
+            Rule::NewIdWithoutInterface => {
+                "A new_id arg has no interface attribute outside of wl_registry.bind."
+            }
+            Rule::MissingDescription => "An interface, message or enum has no description.",
+            Rule::MissingSummary => "An arg, entry or description has no summary attribute.",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Finding {
+    pub(crate) rule: Rule,
+    /// The path of the element, e.g. `wayland/wl_surface/request:attach/arg:buffer`.
+    pub(crate) location: String,
+    pub(crate) message: String,
+}
+
+pub fn main(args: LintArgs) -> Result<(), LintError> {
+    if args.list_rules {
+        for rule in Rule::variants() {
+            println!(
+                "{:<26} {:<8} {}",
+                rule.name(),
+                rule.severity().name(),
+                rule.description(),
+            );
+        }
+        return Ok(());
+    }
+    let mut errors = 0;
+    let mut unparsable = 0;
+    let mut report = |file: &str, findings: Vec<Finding>| {
+        for finding in findings {
+            let severity = finding.rule.severity();
+            if severity == Severity::Error {
+                errors += 1;
+            }
+            println!(
+                "{file}: {}[{}] {}: {}",
+                severity.name(),
+                finding.rule.name(),
+                finding.location,
+                finding.message,
+            );
+        }
+    };
+    if args.paths.is_empty() {
+        let repos = collect();
+        let files: Vec<_> = repos
+            .iter()
+            .flat_map(|r| r.files.iter().map(move |f| (r, f)))
+            .collect();
+        let protocols = parse_files(&files);
+        for ((repo, file), protocols) in files.iter().zip(&protocols) {
+            match protocols {
+                Some(p) => report(&format!("{}/{}", repo.name, file.path), lint(p)),
+                None => unparsable += 1,
+            }
+        }
+    } else {
+        for file in find_xml_files(&args.paths).map_err(LintError::Walk)? {
+            let contents = fs::read(&file).map_err(|e| LintError::Read(file.clone(), e))?;
+            let protocols = match parse(&file, &contents) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("Could not parse {}: {}", file.display(), Report::new(e));
+                    unparsable += 1;
+                    continue;
+                }
+            };
+            report(&file.display().to_string(), lint(&protocols));
+        }
+    }
+    if unparsable > 0 {
+        return Err(LintError::Parse(unparsable));
+    }
+    if errors > 0 {
+        return Err(LintError::Errors(errors));
+    }
+    Ok(())
+}
+
+/// Checks the protocols of a file against all rules.
+pub(crate) fn lint(protocols: &[Protocol]) -> Vec<Finding> {
+    let mut linter = Linter { findings: vec![] };
+    for protocol in protocols {
+        linter.protocol(protocol);
+    }
+    linter.findings
+}
+
+struct Linter {
+    findings: Vec<Finding>,
+}
+
+impl Linter {
+    fn add(&mut self, rule: Rule, location: &str, message: String) {
+        self.findings.push(Finding {
+            rule,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn description(&mut self, location: &str, description: &Option<Description>, required: bool) {
+        match description {
+            None if required => self.add(
+                Rule::MissingDescription,
+                location,
+                "no description".to_string(),
+            ),
+            Some(d) if d.summary.is_none() => self.add(
+                Rule::MissingSummary,
+                location,
+                "the description has no summary".to_string(),
+            ),
+            _ => {}
+        }
+    }
+
+    fn summary(&mut self, location: &str, summary: &Option<String>) {
+        if summary.is_none() {
+            self.add(Rule::MissingSummary, location, "no summary".to_string());
+        }
+    }
+
+    fn versions(
+        &mut self,
+        location: &str,
+        version: u32,
+        since: Option<u32>,
+        deprecated_since: Option<u32>,
+    ) {
+        for (attr, value) in [("since", since), ("deprecated-since", deprecated_since)] {
+            if let Some(value) = value
+                && value > version
+            {
+                self.add(
+                    Rule::SinceExceedsVersion,
+                    location,
+                    format!("{attr} {value} is greater than version {version}"),
+                );
+            }
+        }
+        let since = since.unwrap_or(1);
+        if let Some(deprecated_since) = deprecated_since
+            && deprecated_since <= since
+        {
+            self.add(
+                Rule::DeprecatedBeforeSince,
+                location,
+                format!("deprecated-since {deprecated_since} is not greater than since {since}"),
+            );
+        }
+    }
+
+    fn protocol(&mut self, protocol: &Protocol) {
+        self.description(&protocol.name, &protocol.description, false);
+        for interface in &protocol.interfaces {
+            let location = format!("{}/{}", protocol.name, interface.name);
+            self.interface(&location, interface);
+        }
+    }
+
+    fn interface(&mut self, location: &str, interface: &Interface) {
+        self.description(location, &interface.description, true);
+        for message in &interface.messages {
+            let kind = match message.is_request {
+                true => "request",
+                false => "event",
+            };
+            let location = format!("{location}/{kind}:{}", message.name);
+            self.message(&location, interface, message);
+        }
+        for enum_ in &interface.enums {
+            let location = format!("{location}/enum:{}", enum_.name);
+            self.enum_(&location, interface, enum_);
+        }
+    }
+
+    fn message(&mut self, location: &str, interface: &Interface, message: &Message) {
+        self.description(location, &message.description, true);
+        self.versions(
+            location,
+            interface.version,
+            message.since,
+            message.deprecated_since,
+        );
+        for arg in &message.args {
+            let location = format!("{location}/arg:{}", arg.name);
+            self.summary(&location, &arg.summary);
+            self.description(&location, &arg.description, false);
+            if arg.enum_.is_some() && !matches!(arg.ty, ArgType::Int | ArgType::Uint) {
+                self.add(
+                    Rule::EnumOnNonInteger,
+                    &location,
+                    format!("the arg has an enum attribute but type {}", arg.ty.name()),
+                );
+            }
+            if arg.ty == ArgType::NewId
+                && arg.interface.is_none()
+                && !(interface.name == "wl_registry" && message.name == "bind")
+            {
+                self.add(
+                    Rule::NewIdWithoutInterface,
+                    &location,
+                    "the new_id arg has no interface attribute".to_string(),
+                );
+            }
+        }
+    }
+
+    fn enum_(&mut self, location: &str, interface: &Interface, enum_: &Enum) {
+        self.description(location, &enum_.description, true);
+        self.versions(location, interface.version, enum_.since, None);
+        let mut values = HashMap::new();
+        for entry in &enum_.entries {
+            let location = format!("{location}/entry:{}", entry.name);
+            self.summary(&location, &entry.summary);
+            self.description(&location, &entry.description, false);
+            self.versions(
+                &location,
+                interface.version,
+                entry.since,
+                entry.deprecated_since,
+            );
+            if let Some(previous) = values.insert(entry.value_i64, &entry.name) {
+                self.add(
+                    Rule::DuplicateEntryValue,
+                    &location,
+                    format!("the value {} is also used by {previous}", entry.value),
+                );
+            }
+            let value = entry.value_i64;
+            if enum_.bitfield && value != 0 && (value < 0 || value & (value - 1) != 0) {
+                self.add(
+                    Rule::BitfieldNotPowerOfTwo,
+                    &location,
+                    format!("the value {} is not a power of two", entry.value),
+                );
+            }
+        }
+    }
+}
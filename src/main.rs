@@ -1,12 +1,128 @@
-#![expect(clippy::needless_update, clippy::single_char_add_str)]
+#![expect(clippy::needless_update)]
 
-use {crate::generate::GeneratorError, error_reporter::Report};
+use {
+    crate::{
+        annotate::{AnnotateArgs, AnnotateError},
+        codegen::{CodegenArgs, CodegenError},
+        compat::{CompatArgs, CompatError},
+        decode::{DecodeArgs, DecodeError},
+        dump::{DumpArgs, DumpError},
+        export::{ExportArgs, ExportError},
+        fmt::{FmtArgs, FmtError},
+        generate::{GenerateArgs, GeneratorError},
+        graph::{GraphArgs, GraphError},
+        lint::{LintArgs, LintError},
+        man::{ManArgs, ManError},
+        render::{RenderArgs, RenderError},
+        site::{SiteArgs, SiteError},
+    },
+    clap::{Parser, Subcommand},
+    error_reporter::Report,
+    thiserror::Error,
+};
 
-mod ast;
+mod annotate;
+mod codegen;
 mod collector;
+mod compat;
+mod decode;
+mod dump;
+mod export;
+mod fmt;
 mod generate;
-mod parser;
+mod graph;
+mod lint;
+mod man;
+mod objects;
+mod render;
+mod site;
 
-fn main() -> Result<(), Report<GeneratorError>> {
-    generate::main().map_err(Report::new)
+#[derive(Parser, Debug)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Cmd>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// Generate wayland.db from the repositories (default).
+    Generate(GenerateArgs),
+    /// Write a line-oriented text form of wayland.db for diffing.
+    Dump(DumpArgs),
+    /// Export the protocols in wayland.db in a different format.
+    Export(ExportArgs),
+    /// Rewrite protocol XML files in the canonical style.
+    Fmt(FmtArgs),
+    /// Check protocol files for common mistakes.
+    Lint(LintArgs),
+    /// Check protocol files for incompatible changes against wayland.db.
+    Compat(CompatArgs),
+    /// Generate code from the protocols in wayland.db.
+    Codegen(CodegenArgs),
+    /// Render user-defined templates with the protocols in wayland.db.
+    Render(RenderArgs),
+    /// Generate a static HTML site from wayland.db.
+    Site(SiteArgs),
+    /// Generate man pages for the interfaces in wayland.db.
+    Man(ManArgs),
+    /// Write the protocol dependency, interface reference, or object creation
+    /// graph in Graphviz or Mermaid format.
+    Graph(GraphArgs),
+    /// Annotate a WAYLAND_DEBUG log with information from wayland.db.
+    Annotate(AnnotateArgs),
+    /// Decode captured wire-protocol traffic with the protocols in wayland.db.
+    Decode(DecodeArgs),
+}
+
+#[derive(Debug, Error)]
+enum MainError {
+    #[error("could not generate the database")]
+    Generate(#[from] GeneratorError),
+    #[error("could not dump the database")]
+    Dump(#[from] DumpError),
+    #[error("could not export the database")]
+    Export(#[from] ExportError),
+    #[error("could not format the protocol files")]
+    Fmt(#[from] FmtError),
+    #[error("could not lint the protocol files")]
+    Lint(#[from] LintError),
+    #[error("could not check the compatibility of the protocol files")]
+    Compat(#[from] CompatError),
+    #[error("could not generate code")]
+    Codegen(#[from] CodegenError),
+    #[error("could not render the templates")]
+    Render(#[from] RenderError),
+    #[error("could not generate the site")]
+    Site(#[from] SiteError),
+    #[error("could not generate the man pages")]
+    Man(#[from] ManError),
+    #[error("could not write the graph")]
+    Graph(#[from] GraphError),
+    #[error("could not annotate the log")]
+    Annotate(#[from] AnnotateError),
+    #[error("could not decode the traffic")]
+    Decode(#[from] DecodeError),
+}
+
+fn main() -> Result<(), Report<MainError>> {
+    let cli = Cli::parse();
+    let res = match cli
+        .command
+        .unwrap_or_else(|| Cmd::Generate(Default::default()))
+    {
+        Cmd::Generate(args) => generate::main(args).map_err(MainError::from),
+        Cmd::Dump(args) => dump::main(args).map_err(MainError::from),
+        Cmd::Export(args) => export::main(args).map_err(MainError::from),
+        Cmd::Fmt(args) => fmt::main(args).map_err(MainError::from),
+        Cmd::Lint(args) => lint::main(args).map_err(MainError::from),
+        Cmd::Compat(args) => compat::main(args).map_err(MainError::from),
+        Cmd::Codegen(args) => codegen::main(args).map_err(MainError::from),
+        Cmd::Render(args) => render::main(args).map_err(MainError::from),
+        Cmd::Site(args) => site::main(args).map_err(MainError::from),
+        Cmd::Man(args) => man::main(args).map_err(MainError::from),
+        Cmd::Graph(args) => graph::main(args).map_err(MainError::from),
+        Cmd::Annotate(args) => annotate::main(args).map_err(MainError::from),
+        Cmd::Decode(args) => decode::main(args).map_err(MainError::from),
+    };
+    res.map_err(Report::new)
 }
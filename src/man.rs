@@ -0,0 +1,367 @@
+use {
+    crate::export::is_file_name,
+    clap::{Args, ValueEnum},
+    std::{
+        collections::{HashMap, HashSet},
+        fmt::Write,
+        fs, io,
+        path::PathBuf,
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, Description, Interface, Message, MessageType, Protocol},
+        db::{Database, DbError, Model, Repo},
+        text::format_ml_text,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum ManError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("`{0}` cannot be used as a file name")]
+    FileName(String),
+}
+
+#[derive(Args, Debug)]
+pub struct ManArgs {
+    /// The database to read the interfaces from.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The directory to write the man pages to.
+    #[clap(long)]
+    output: PathBuf,
+    /// The manual section of the pages.
+    #[clap(long, value_enum, default_value_t = Section::Library)]
+    section: Section,
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum Section {
+    /// Section 3, library calls.
+    #[value(name = "3")]
+    Library,
+    /// Section 7, miscellaneous.
+    #[value(name = "7")]
+    Misc,
+}
+
+impl Section {
+    fn number(self) -> &'static str {
+        match self {
+            Section::Library => "3",
+            Section::Misc => "7",
+        }
+    }
+}
+
+pub fn main(args: ManArgs) -> Result<(), ManError> {
+    let model = Database::open(&args.db)?.load()?;
+    let pages = page_names(&model);
+    fs::create_dir_all(&args.output).map_err(|e| ManError::CreateDir(args.output.clone(), e))?;
+    for repo in &model.repos {
+        for protocol in &repo.protocols {
+            for interface in &protocol.interfaces {
+                let page = Page {
+                    model: &model,
+                    pages: &pages,
+                    section: args.section,
+                    repo,
+                    protocol,
+                    interface,
+                    out: String::new(),
+                };
+                let name = &pages[&(&*repo.name, &*protocol.name, &*interface.name)];
+                if !is_file_name(name) {
+                    return Err(ManError::FileName(name.clone()));
+                }
+                let file = args
+                    .output
+                    .join(format!("{name}.{}", args.section.number()));
+                fs::write(&file, page.render()).map_err(|e| ManError::Write(file, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+type PageNames<'a> = HashMap<(&'a str, &'a str, &'a str), String>;
+
+/// Assigns a page name to every interface.
+///
+/// The first interface with a name uses the plain name. Interfaces with the same
+/// name in later repos or protocols are prefixed with the repo and protocol.
+fn page_names(model: &Model) -> PageNames<'_> {
+    let mut used = HashSet::new();
+    let mut names = HashMap::new();
+    for repo in &model.repos {
+        for protocol in &repo.protocols {
+            for interface in &protocol.interfaces {
+                let mut name = interface.name.clone();
+                if !used.insert(name.clone()) {
+                    name = format!("{}-{}-{}", repo.name, protocol.name, interface.name);
+                    used.insert(name.clone());
+                }
+                names.insert((&*repo.name, &*protocol.name, &*interface.name), name);
+            }
+        }
+    }
+    names
+}
+
+struct Page<'a> {
+    model: &'a Model,
+    pages: &'a PageNames<'a>,
+    section: Section,
+    repo: &'a Repo,
+    protocol: &'a Protocol,
+    interface: &'a Interface,
+    out: String,
+}
+
+impl Page<'_> {
+    fn render(mut self) -> String {
+        let interface = self.interface;
+        let _ = writeln!(
+            self.out,
+            ".TH \"{}\" \"{}\" \"\" \"wayland-db\" \"Wayland Protocols\"",
+            escape(&interface.name),
+            self.section.number(),
+        );
+        self.out.push_str(".SH NAME\n");
+        let _ = write!(self.out, "{}", escape(&interface.name));
+        if let Some(summary) = Description::summary_of(&interface.description) {
+            let _ = write!(self.out, " \\- {}", escape(summary));
+        }
+        self.out.push('\n');
+        self.synopsis();
+        if let Some(description) = &interface.description {
+            self.out.push_str(".SH DESCRIPTION\n");
+            self.text(&description.body);
+        }
+        for (is_request, title) in [(true, "REQUESTS"), (false, "EVENTS")] {
+            let mut messages = interface.messages_of_kind(is_request).peekable();
+            if messages.peek().is_none() {
+                continue;
+            }
+            let _ = writeln!(self.out, ".SH {title}");
+            for message in messages {
+                self.message(message);
+            }
+        }
+        if !interface.enums.is_empty() {
+            self.out.push_str(".SH ENUMS\n");
+            self.enums();
+        }
+        self.see_also();
+        self.out
+    }
+
+    fn synopsis(&mut self) {
+        let interface = self.interface;
+        self.out.push_str(".SH SYNOPSIS\n");
+        let _ = writeln!(
+            self.out,
+            "Interface of the {} protocol in {}, version {}.",
+            escape(&self.protocol.name),
+            escape(&self.repo.name),
+            interface.version,
+        );
+        for (is_request, title) in [(true, "Requests"), (false, "Events")] {
+            let mut messages = interface.messages_of_kind(is_request).peekable();
+            if messages.peek().is_none() {
+                continue;
+            }
+            let _ = writeln!(self.out, ".SS {title}\n.nf");
+            for message in messages {
+                let args: Vec<_> = message
+                    .args
+                    .iter()
+                    .map(|a| format!("{} {}", arg_type(a), a.name))
+                    .collect();
+                let _ = write!(
+                    self.out,
+                    "\\fB{}\\fR({})",
+                    escape(&message.name),
+                    escape(&args.join(", "))
+                );
+                if let Some(since) = message.since {
+                    let _ = write!(self.out, " [since {since}]");
+                }
+                self.out.push('\n');
+            }
+            self.out.push_str(".fi\n");
+        }
+    }
+
+    fn message(&mut self, message: &Message) {
+        let _ = writeln!(self.out, ".SS {}", escape(&message.name));
+        let mut notes = vec![];
+        if message.ty == Some(MessageType::Destructor) {
+            notes.push("Destructor.".to_string());
+        }
+        if let Some(since) = message.since {
+            notes.push(format!("Since version {since}."));
+        }
+        if let Some(deprecated_since) = message.deprecated_since {
+            notes.push(format!("Deprecated since version {deprecated_since}."));
+        }
+        if let Some(summary) = Description::summary_of(&message.description) {
+            let _ = writeln!(self.out, "{}", line(summary));
+        }
+        if !notes.is_empty() {
+            let _ = writeln!(self.out, ".PP\n{}", line(&notes.join(" ")));
+        }
+        if let Some(description) = &message.description {
+            self.out.push_str(".PP\n");
+            self.text(&description.body);
+        }
+        for arg in &message.args {
+            let _ = writeln!(
+                self.out,
+                ".TP\n\\fI{}\\fR ({})",
+                escape(&arg.name),
+                escape(&arg_type(arg)),
+            );
+            if let Some(summary) = arg.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+                let _ = writeln!(self.out, "{}", line(summary));
+            }
+        }
+    }
+
+    fn enums(&mut self) {
+        for enum_ in &self.interface.enums {
+            let _ = writeln!(self.out, ".SS {}", escape(&enum_.name));
+            let mut notes = vec![];
+            if enum_.bitfield {
+                notes.push("Bitfield.".to_string());
+            }
+            if let Some(since) = enum_.since {
+                notes.push(format!("Since version {since}."));
+            }
+            if let Some(summary) = Description::summary_of(&enum_.description) {
+                let _ = writeln!(self.out, "{}", line(summary));
+            }
+            if !notes.is_empty() {
+                let _ = writeln!(self.out, ".PP\n{}", line(&notes.join(" ")));
+            }
+            if let Some(description) = &enum_.description {
+                self.out.push_str(".PP\n");
+                self.text(&description.body);
+            }
+            for entry in &enum_.entries {
+                let _ = writeln!(
+                    self.out,
+                    ".TP\n\\fB{}\\fR = {}",
+                    escape(&entry.name),
+                    escape(&entry.value),
+                );
+                let mut text = entry.summary.clone().unwrap_or_default();
+                if let Some(since) = entry.since {
+                    let _ = write!(text, " (since version {since})");
+                }
+                if let Some(deprecated_since) = entry.deprecated_since {
+                    let _ = write!(text, " (deprecated since version {deprecated_since})");
+                }
+                if !text.trim().is_empty() {
+                    let _ = writeln!(self.out, "{}", line(&text));
+                }
+            }
+        }
+    }
+
+    /// Writes links to the pages of the interfaces referenced by args.
+    fn see_also(&mut self) {
+        let mut seen = HashSet::new();
+        let mut links = vec![];
+        for message in &self.interface.messages {
+            for arg in &message.args {
+                let path = Model::arg_path(self.repo, self.protocol, self.interface, message, arg);
+                let targets = self.model.arg_interfaces.get(&path).map(|v| &v[..]);
+                for target in targets.unwrap_or_default() {
+                    let key = (&*target.repo, &*target.protocol, &*target.interface);
+                    if key
+                        == (
+                            &*self.repo.name,
+                            &*self.protocol.name,
+                            &*self.interface.name,
+                        )
+                    {
+                        continue;
+                    }
+                    if let Some(page) = self.pages.get(&key)
+                        && seen.insert(page)
+                    {
+                        links.push(page);
+                    }
+                }
+            }
+        }
+        if links.is_empty() {
+            return;
+        }
+        links.sort();
+        self.out.push_str(".SH SEE ALSO\n");
+        for (idx, page) in links.iter().enumerate() {
+            let comma = if idx + 1 < links.len() { "," } else { "" };
+            let _ = writeln!(
+                self.out,
+                ".BR {} ({}){comma}",
+                escape(page),
+                self.section.number()
+            );
+        }
+    }
+
+    /// Writes a description with one paragraph per block of non-empty lines.
+    fn text(&mut self, body: &str) {
+        let body = format_ml_text(body);
+        let mut first = true;
+        for paragraph in body.split("\n\n") {
+            if paragraph.trim().is_empty() {
+                continue;
+            }
+            if !first {
+                self.out.push_str(".PP\n");
+            }
+            first = false;
+            for l in paragraph.lines().filter(|l| !l.trim().is_empty()) {
+                let _ = writeln!(self.out, "{}", line(l.trim_end()));
+            }
+        }
+    }
+}
+
+/// Formats the type of an arg as in `?object<wl_surface>` or `uint<enum>`.
+fn arg_type(arg: &Arg) -> String {
+    let mut res = String::new();
+    if arg.allow_null {
+        res.push('?');
+    }
+    res.push_str(arg.ty.name());
+    if let Some(interface) = &arg.interface {
+        let _ = write!(res, "<{interface}>");
+    }
+    if let Some(enum_) = &arg.enum_ {
+        let _ = write!(res, "<{enum_}>");
+    }
+    res
+}
+
+/// Escapes text for roff.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// Escapes a line of text so that it is not interpreted as a request.
+fn line(s: &str) -> String {
+    let s = escape(s.trim_start());
+    match s.starts_with(['.', '\'']) {
+        true => format!("\\&{s}"),
+        false => s,
+    }
+}
@@ -0,0 +1,315 @@
+use {
+    std::collections::{HashMap, HashSet, hash_map::Entry},
+    wayland_db::{
+        ast::{Arg, Enum, Interface, Message, Protocol},
+        db::{Model, Repo},
+    },
+};
+
+/// An interface of the database with its ancestors.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Resolved<'a> {
+    pub(crate) repo: &'a Repo,
+    pub(crate) protocol: &'a Protocol,
+    pub(crate) interface: &'a Interface,
+}
+
+impl<'a> Resolved<'a> {
+    /// Returns the message with the given name.
+    ///
+    /// If the interface has a request and an event with this name, the one
+    /// matching `is_request` is returned.
+    pub(crate) fn message(&self, name: &str, is_request: bool) -> Option<&'a Message> {
+        let mut candidates = self.interface.messages.iter().filter(|m| m.name == name);
+        let first = candidates.next()?;
+        match candidates.next() {
+            Some(second) if second.is_request == is_request => Some(second),
+            _ => Some(first),
+        }
+    }
+
+    /// Returns the message with the given opcode.
+    pub(crate) fn message_by_opcode(&self, opcode: u16, is_request: bool) -> Option<&'a Message> {
+        self.interface
+            .messages
+            .iter()
+            .find(|m| m.is_request == is_request && m.message_id == opcode as usize)
+    }
+}
+
+/// The interfaces of the database indexed by name.
+pub(crate) struct Index<'a> {
+    model: &'a Model,
+    interfaces: HashMap<&'a str, Vec<Resolved<'a>>>,
+    by_path: HashMap<(&'a str, &'a str, &'a str), Resolved<'a>>,
+}
+
+impl<'a> Index<'a> {
+    /// Creates an index in which interfaces from the repos in `prefer` come
+    /// first, in the order of `prefer`.
+    pub(crate) fn new(model: &'a Model, prefer: &[String]) -> Self {
+        let mut interfaces = HashMap::<_, Vec<_>>::new();
+        let mut by_path = HashMap::new();
+        for repo in &model.repos {
+            for protocol in &repo.protocols {
+                for interface in &protocol.interfaces {
+                    let resolved = Resolved {
+                        repo,
+                        protocol,
+                        interface,
+                    };
+                    interfaces
+                        .entry(&*interface.name)
+                        .or_default()
+                        .push(resolved);
+                    by_path.insert((&*repo.name, &*protocol.name, &*interface.name), resolved);
+                }
+            }
+        }
+        for candidates in interfaces.values_mut() {
+            candidates.sort_by_key(|c| {
+                prefer
+                    .iter()
+                    .position(|p| *p == c.repo.name)
+                    .unwrap_or(prefer.len())
+            });
+        }
+        Self {
+            model,
+            interfaces,
+            by_path,
+        }
+    }
+
+    /// Returns the interface with the given name.
+    pub(crate) fn find(&self, name: &str) -> Option<Resolved<'a>> {
+        self.interfaces.get(name).and_then(|c| c.first()).copied()
+    }
+
+    /// Returns the enum that the `enum` attribute of an arg resolves to.
+    pub(crate) fn arg_enum(
+        &self,
+        interface: Resolved<'a>,
+        message: &Message,
+        arg: &Arg,
+    ) -> Option<&'a Enum> {
+        let path = Model::arg_path(
+            interface.repo,
+            interface.protocol,
+            interface.interface,
+            message,
+            arg,
+        );
+        let target = self.model.arg_enums.get(&path)?.first()?;
+        let key = (&*target.repo, &*target.protocol, &*target.interface);
+        let interface = self.by_path.get(&key)?;
+        interface
+            .interface
+            .enums
+            .iter()
+            .find(|e| e.name == target.enum_)
+    }
+}
+
+/// Formats an enum value with the names of its entries, e.g. `5 (top|left)`.
+pub(crate) fn format_enum(enum_: &Enum, value: i64) -> String {
+    format!("{value} ({})", entry_names(enum_, value))
+}
+
+/// Returns the names of the entries of an enum value, e.g. `top|left`.
+///
+/// Bitfields are decomposed into entries, preferring entries that cover more
+/// bits. Bits without an entry are appended in hexadecimal.
+pub(crate) fn entry_names(enum_: &Enum, value: i64) -> String {
+    if !enum_.bitfield || value == 0 {
+        return match enum_.entries.iter().find(|e| e.value_i64 == value) {
+            Some(entry) => entry.name.clone(),
+            None => "unknown".to_string(),
+        };
+    }
+    let mut entries: Vec<_> = enum_.entries.iter().filter(|e| e.value_i64 != 0).collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.value_i64.count_ones()));
+    let mut names = vec![];
+    let mut covered = 0;
+    for entry in entries {
+        if value & entry.value_i64 == entry.value_i64 && entry.value_i64 & !covered != 0 {
+            covered |= entry.value_i64;
+            names.push(entry.name.clone());
+        }
+    }
+    if value & !covered != 0 {
+        names.push(format!("{:#x}", value & !covered));
+    }
+    names.join("|")
+}
+
+/// Returns warnings if a message does not exist at the version of an object or
+/// is deprecated at that version.
+pub(crate) fn message_warnings(
+    interface: &str,
+    id: u32,
+    message: &Message,
+    version: u32,
+) -> Vec<String> {
+    let mut res = vec![];
+    let since = message.since.unwrap_or(1);
+    if since > version {
+        res.push(format!(
+            "{interface}.{} requires version {since} but {interface}#{id} has version {version}",
+            message.name,
+        ));
+    }
+    if let Some(deprecated_since) = message.deprecated_since
+        && deprecated_since <= version
+    {
+        res.push(format!(
+            "{interface}.{} is deprecated since version {deprecated_since}",
+            message.name,
+        ));
+    }
+    res
+}
+
+/// Returns warnings for the entries of an enum value that do not exist at a
+/// version or are deprecated at that version.
+pub(crate) fn entry_warnings(enum_: &Enum, value: i64, version: u32) -> Vec<String> {
+    let mut res = vec![];
+    for entry in &enum_.entries {
+        let matches = match enum_.bitfield {
+            true => entry.value_i64 != 0 && value & entry.value_i64 == entry.value_i64,
+            false => entry.value_i64 == value,
+        };
+        if !matches {
+            continue;
+        }
+        let since = entry.since.unwrap_or(1);
+        if since > version {
+            res.push(format!(
+                "{}.{} requires version {since}",
+                enum_.name, entry.name,
+            ));
+        }
+        if let Some(deprecated_since) = entry.deprecated_since
+            && deprecated_since <= version
+        {
+            res.push(format!(
+                "{}.{} is deprecated since version {deprecated_since}",
+                enum_.name, entry.name,
+            ));
+        }
+    }
+    res
+}
+
+/// Returns a warning if a global is bound with a version that is higher than
+/// the version of its interface.
+pub(crate) fn bind_warning(index: &Index<'_>, interface: &str, version: u32) -> Option<String> {
+    let resolved = index.find(interface)?;
+    (version > resolved.interface.version).then(|| {
+        format!(
+            "{interface} is bound with version {version} but has version {}",
+            resolved.interface.version,
+        )
+    })
+}
+
+/// The first object ID allocated by the server.
+const SERVER_ID_START: u32 = 0xff000000;
+
+/// An object of a connection.
+#[derive(Debug)]
+pub(crate) struct Object<'a> {
+    /// The name of the interface as it appears in the traffic.
+    pub(crate) name: String,
+    /// The interface in the database or `None` if it is unknown.
+    pub(crate) interface: Option<Resolved<'a>>,
+    /// The version or `None` if the object was not created in the traffic.
+    pub(crate) version: Option<u32>,
+    /// The destructor that was sent for the object.
+    pub(crate) destroyed: Option<&'a Message>,
+}
+
+/// Tracks the interfaces and versions of the objects of a connection.
+pub(crate) struct Objects<'a> {
+    index: &'a Index<'a>,
+    objects: HashMap<u32, Object<'a>>,
+    /// The IDs that were released and not reused by a new object.
+    deleted: HashSet<u32>,
+}
+
+impl<'a> Objects<'a> {
+    /// Creates a tracker that contains only `wl_display#1`.
+    pub(crate) fn new(index: &'a Index<'a>) -> Self {
+        let mut res = Self {
+            index,
+            objects: HashMap::new(),
+            deleted: HashSet::new(),
+        };
+        res.create(1, "wl_display", Some(1));
+        res
+    }
+
+    pub(crate) fn get(&self, id: u32) -> Option<&Object<'a>> {
+        self.objects.get(&id)
+    }
+
+    /// Returns whether the ID was released and has not been reused since.
+    pub(crate) fn is_deleted(&self, id: u32) -> bool {
+        self.deleted.contains(&id)
+    }
+
+    /// Adds an object that was not created in the traffic, for example because
+    /// the capture started late.
+    pub(crate) fn assume(&mut self, id: u32, name: &str) -> &Object<'a> {
+        let index = self.index;
+        self.objects.entry(id).or_insert_with(|| Object {
+            name: name.to_string(),
+            interface: index.find(name),
+            version: None,
+            destroyed: None,
+        })
+    }
+
+    /// Adds an object and returns a warning if the ID is still in use.
+    pub(crate) fn create(&mut self, id: u32, name: &str, version: Option<u32>) -> Option<String> {
+        let object = Object {
+            name: name.to_string(),
+            interface: self.index.find(name),
+            version,
+            destroyed: None,
+        };
+        self.deleted.remove(&id);
+        match self.objects.entry(id) {
+            Entry::Occupied(mut o) => {
+                let old = o.insert(object);
+                Some(format!(
+                    "{name}#{id} was created while {}#{id} was still in use",
+                    old.name
+                ))
+            }
+            Entry::Vacant(v) => {
+                v.insert(object);
+                None
+            }
+        }
+    }
+
+    /// Marks an object as destroyed.
+    ///
+    /// IDs allocated by the client stay in use until the server deletes them.
+    /// IDs allocated by the server are released immediately.
+    pub(crate) fn destroy(&mut self, id: u32, destructor: &'a Message) {
+        if id >= SERVER_ID_START {
+            self.delete(id);
+        } else if let Some(object) = self.objects.get_mut(&id) {
+            object.destroyed = Some(destructor);
+        }
+    }
+
+    /// Releases the ID of an object, e.g. after `wl_display.delete_id`.
+    pub(crate) fn delete(&mut self, id: u32) -> Option<Object<'a>> {
+        let object = self.objects.remove(&id)?;
+        self.deleted.insert(id);
+        Some(object)
+    }
+}
@@ -1,12 +1,13 @@
 use {
     crate::ast::{
-        Arg, ArgType, Copyright, Description, Entry, Enum, Interface, Message, MessageType,
-        Protocol,
+        Arg, ArgType, Copyright, Description, Entry, Enum, Interface, InterfaceElement, Message,
+        MessageType, Protocol,
     },
     quick_xml::{
         Reader,
+        escape::resolve_predefined_entity,
         events::{
-            Event,
+            BytesRef, Event,
             attributes::{AttrError, Attribute, Attributes},
         },
     },
@@ -48,6 +49,8 @@ pub enum ProtocolError {
 pub enum CopyrightError {
     #[error("could not read the next event")]
     ReadEvent(#[from] quick_xml::Error),
+    #[error("could not resolve a reference")]
+    ResolveReference(#[source] ReferenceError),
     #[error("could not decode the body as UTF-8")]
     DecodeUtf8(#[source] FromUtf8Error),
 }
@@ -58,6 +61,8 @@ pub enum DescriptionError {
     Attribute(#[from] AttributeError),
     #[error("could not read the next event")]
     ReadEvent(#[from] quick_xml::Error),
+    #[error("could not resolve a reference")]
+    ResolveReference(#[source] ReferenceError),
     #[error("could not decode the body as UTF-8")]
     DecodeUtf8(#[source] FromUtf8Error),
 }
@@ -160,6 +165,14 @@ pub enum EntryError {
     DeprecatedSince(#[source] ParseIntError),
 }
 
+#[derive(Debug, Error)]
+pub enum ReferenceError {
+    #[error("quick_xml returned an error")]
+    QuickXml(#[from] quick_xml::Error),
+    #[error("unknown entity `&{0};`")]
+    UnknownEntity(String),
+}
+
 #[derive(Debug, Error)]
 pub enum AttributeError {
     #[error("quick_xml returned an error")]
@@ -168,7 +181,10 @@ pub enum AttributeError {
     DecodeUtf8(#[from] quick_xml::Error),
 }
 
-pub(crate) fn parse(path: &Path, input: &[u8]) -> Result<Vec<Protocol>, ParserError> {
+/// Parses the `protocol` elements of a protocol file.
+///
+/// `path` is only used to fill in [`Protocol::path`].
+pub fn parse(path: &Path, input: &[u8]) -> Result<Vec<Protocol>, ParserError> {
     let mut reader = Reader::from_reader(input);
     let mut protocols = Vec::new();
     loop {
@@ -207,6 +223,22 @@ fn parse_attr<'a>(attr: &'a Attribute) -> Result<(&'a [u8], Cow<'a, str>), Attri
     Ok((name, value))
 }
 
+/// Appends the text that a `&...;` reference in element content stands for.
+///
+/// Only the predefined entities of XML are known since protocol files do not
+/// have a DTD.
+fn resolve_reference(r: &BytesRef<'_>, body: &mut Vec<u8>) -> Result<(), ReferenceError> {
+    if let Some(c) = r.resolve_char_ref()? {
+        body.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        return Ok(());
+    }
+    let name = r.decode().map_err(quick_xml::Error::from)?;
+    let s = resolve_predefined_entity(&name)
+        .ok_or_else(|| ReferenceError::UnknownEntity(name.to_string()))?;
+    body.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
 fn parse_protocol(
     path: &Path,
     reader: &mut Reader<&[u8]>,
@@ -269,6 +301,9 @@ fn parse_copyright(
             match event {
                 Event::Text(s) => body.extend_from_slice(s.as_ref()),
                 Event::CData(s) => body.extend_from_slice(s.as_ref()),
+                Event::GeneralRef(r) => {
+                    resolve_reference(&r, &mut body).map_err(CopyrightError::ResolveReference)?
+                }
                 Event::End(_) => break,
                 _ => continue,
             }
@@ -298,6 +333,9 @@ fn parse_description(
             let event = reader.read_event().map_err(DescriptionError::ReadEvent)?;
             match event {
                 Event::Text(s) => body.extend_from_slice(s.as_ref()),
+                Event::GeneralRef(r) => {
+                    resolve_reference(&r, &mut body).map_err(DescriptionError::ResolveReference)?
+                }
                 Event::End(_) => break,
                 _ => continue,
             }
@@ -327,6 +365,7 @@ fn parse_interface(
     let mut description = None;
     let mut messages = Vec::new();
     let mut enums = Vec::new();
+    let mut elements = Vec::new();
     if !empty {
         let mut num_requests = 0;
         let mut num_events = 0;
@@ -342,15 +381,24 @@ fn parse_interface(
                 b"description" => {
                     description = Some(parse_description(reader, start.attributes(), empty)?)
                 }
-                b"request" => messages.push(
-                    parse_message(reader, start.attributes(), empty, &mut num_requests, true)
-                        .map_err(InterfaceError::Request)?,
-                ),
-                b"event" => messages.push(
-                    parse_message(reader, start.attributes(), empty, &mut num_events, false)
-                        .map_err(InterfaceError::Event)?,
-                ),
-                b"enum" => enums.push(parse_enum(reader, start.attributes(), empty)?),
+                b"request" => {
+                    elements.push(InterfaceElement::Message(messages.len()));
+                    messages.push(
+                        parse_message(reader, start.attributes(), empty, &mut num_requests, true)
+                            .map_err(InterfaceError::Request)?,
+                    );
+                }
+                b"event" => {
+                    elements.push(InterfaceElement::Message(messages.len()));
+                    messages.push(
+                        parse_message(reader, start.attributes(), empty, &mut num_events, false)
+                            .map_err(InterfaceError::Event)?,
+                    );
+                }
+                b"enum" => {
+                    elements.push(InterfaceElement::Enum(enums.len()));
+                    enums.push(parse_enum(reader, start.attributes(), empty)?);
+                }
                 _ => continue,
             }
         }
@@ -362,6 +410,7 @@ fn parse_interface(
         description,
         messages,
         enums,
+        elements,
     })
 }
 
@@ -440,19 +489,10 @@ fn parse_arg(
         let (n, value) = parse_attr!(attr)?;
         match n {
             b"name" => name = Some(value.into_owned()),
-            b"type" => {
-                ty = Some(match value.as_ref() {
-                    "int" => ArgType::Int,
-                    "uint" => ArgType::Uint,
-                    "fixed" => ArgType::Fixed,
-                    "string" => ArgType::String,
-                    "array" => ArgType::Array,
-                    "fd" => ArgType::Fd,
-                    "new_id" => ArgType::NewId,
-                    "object" => ArgType::Object,
-                    _ => return Err(ArgError::UnknownArgType(value.into_owned())),
-                })
-            }
+            b"type" => match ArgType::from_name(&value) {
+                Some(t) => ty = Some(t),
+                None => return Err(ArgError::UnknownArgType(value.into_owned())),
+            },
             b"summary" => summary = Some(value.into_owned()),
             b"interface" => interface = Some(value.into_owned()),
             b"allow-null" => allow_null = Some(value.parse().map_err(ArgError::AllowNull)?),
@@ -577,7 +617,7 @@ fn parse_entry(
         }
     }
     let value_string = value.ok_or(EntryError::MissingValue)?;
-    let mut negative = true;
+    let mut negative = false;
     let mut value = &*value_string;
     if let Some(v) = value.strip_prefix("-") {
         negative = true;
@@ -0,0 +1,148 @@
+use {
+    crate::export::json_repos,
+    clap::Args,
+    minijinja::{Environment, UndefinedBehavior, Value, context},
+    std::{
+        fs, io,
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+    walkdir::WalkDir,
+    wayland_db::db::{Database, DbError},
+};
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("could not search for templates")]
+    Walk(#[source] walkdir::Error),
+    #[error("could not read {}", .0.display())]
+    Read(PathBuf, #[source] io::Error),
+    #[error("could not render {0}")]
+    Template(String, #[source] minijinja::Error),
+    #[error("could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+}
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// The directory containing the templates.
+    #[clap(long)]
+    template: PathBuf,
+    /// The directory to write the rendered files to.
+    #[clap(long)]
+    output: PathBuf,
+    /// The database to render.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+}
+
+pub fn main(args: RenderArgs) -> Result<(), RenderError> {
+    let model = Database::open(&args.db)?.load()?;
+    let mut sources = vec![];
+    for entry in WalkDir::new(&args.template).sort_by_file_name() {
+        let entry = entry.map_err(RenderError::Walk)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let name = path.strip_prefix(&args.template).unwrap_or(path);
+        let name = name.to_string_lossy().replace('\\', "/");
+        let source =
+            fs::read_to_string(path).map_err(|e| RenderError::Read(path.to_path_buf(), e))?;
+        sources.push((name, source));
+    }
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+    env.add_filter("camel_case", camel_case);
+    for (name, source) in &sources {
+        env.add_template(name, source)
+            .map_err(|e| RenderError::Template(name.clone(), e))?;
+    }
+    let repos = Value::from_serialize(json_repos(&model));
+    for (name, _) in &sources {
+        // Partials can only be used by other templates.
+        if name.split('/').any(|c| c.starts_with('_')) {
+            continue;
+        }
+        render(&env, &args.output, name, &repos)?;
+    }
+    Ok(())
+}
+
+/// Renders a template once or once per repo, protocol, or interface depending
+/// on which of these variables its path uses.
+fn render(
+    env: &Environment<'_>,
+    output: &Path,
+    name: &str,
+    repos: &Value,
+) -> Result<(), RenderError> {
+    let err = |e| RenderError::Template(name.to_string(), e);
+    let template = env.get_template(name).map_err(err)?;
+    let path = env
+        .template_from_str(name.strip_suffix(".jinja").unwrap_or(name))
+        .map_err(err)?;
+    let vars = path.undeclared_variables(false);
+    let per_interface = vars.contains("interface");
+    let per_protocol = per_interface || vars.contains("protocol");
+    let per_repo = per_protocol || vars.contains("repo");
+    let mut contexts = vec![];
+    if !per_repo {
+        contexts.push(context! { repos });
+    }
+    for repo in repos.try_iter().map_err(err)?.filter(|_| per_repo) {
+        if !per_protocol {
+            contexts.push(context! { repos, repo });
+            continue;
+        }
+        for protocol in repo
+            .get_attr("protocols")
+            .and_then(|p| p.try_iter())
+            .map_err(err)?
+        {
+            if !per_interface {
+                contexts.push(context! { repos, repo, protocol });
+                continue;
+            }
+            let interfaces = protocol.get_attr("interfaces").and_then(|i| i.try_iter());
+            for interface in interfaces.map_err(err)? {
+                contexts.push(context! { repos, repo, protocol, interface });
+            }
+        }
+    }
+    for ctx in contexts {
+        let contents = template.render(&ctx).map_err(err)?;
+        // Templates can skip files by rendering only whitespace.
+        if contents.trim().is_empty() {
+            continue;
+        }
+        let file = output.join(path.render(&ctx).map_err(err)?);
+        write(&file, &contents)?;
+    }
+    Ok(())
+}
+
+fn write(file: &Path, contents: &str) -> Result<(), RenderError> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| RenderError::CreateDir(dir.to_path_buf(), e))?;
+    }
+    fs::write(file, contents).map_err(|e| RenderError::Write(file.to_path_buf(), e))
+}
+
+/// Converts a snake_case name to CamelCase.
+fn camel_case(name: &str) -> String {
+    let mut res = String::new();
+    for part in name.split('_') {
+        let mut chars = part.chars();
+        if let Some(c) = chars.next() {
+            res.push(c.to_ascii_uppercase());
+            res.extend(chars);
+        }
+    }
+    res
+}
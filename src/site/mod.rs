@@ -0,0 +1,492 @@
+use {
+    crate::export::is_file_name,
+    clap::Args,
+    serde::Serialize,
+    std::{
+        collections::HashMap,
+        fmt::Write,
+        fs, io,
+        path::{Path, PathBuf},
+    },
+    thiserror::Error,
+    wayland_db::{
+        ast::{Arg, Description, Enum, Interface, Message, MessageType, Protocol},
+        db::{Database, DbError, Model, Repo},
+        text::escape_xml,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum SiteError {
+    #[error("could not load the database")]
+    Db(#[from] DbError),
+    #[error("could not serialize the search index")]
+    SearchIndex(#[source] serde_json::Error),
+    #[error("could not create {}", .0.display())]
+    CreateDir(PathBuf, #[source] io::Error),
+    #[error("could not write {}", .0.display())]
+    Write(PathBuf, #[source] io::Error),
+    #[error("`{0}` cannot be used as a file name")]
+    FileName(String),
+}
+
+#[derive(Args, Debug)]
+pub struct SiteArgs {
+    /// The database to render.
+    #[clap(long, default_value = "wayland.db")]
+    db: PathBuf,
+    /// The directory to write the site to.
+    #[clap(long)]
+    output: PathBuf,
+}
+
+pub fn main(args: SiteArgs) -> Result<(), SiteError> {
+    let model = Database::open(&args.db)?.load()?;
+    let mut site = Site {
+        model: &model,
+        referenced_by: HashMap::new(),
+        search: vec![],
+    };
+    site.collect_references();
+    let out = &args.output;
+    write(&out.join("style.css"), include_str!("style.css"))?;
+    write(&out.join("search.js"), include_str!("search.js"))?;
+    for repo in &model.repos {
+        for protocol in &repo.protocols {
+            let names = protocol.interfaces.iter().map(|i| &i.name);
+            for name in [&repo.name, &protocol.name].into_iter().chain(names) {
+                if !is_file_name(name) {
+                    return Err(SiteError::FileName(name.clone()));
+                }
+            }
+            let dir = out.join(&repo.name).join(&protocol.name);
+            write(&dir.join("index.html"), &site.protocol_page(repo, protocol))?;
+            for interface in &protocol.interfaces {
+                let file = dir.join(format!("{}.html", interface.name));
+                write(&file, &site.interface_page(repo, protocol, interface))?;
+            }
+        }
+    }
+    write(&out.join("index.html"), &site.index_page())?;
+    let index = serde_json::to_string(&site.search).map_err(SiteError::SearchIndex)?;
+    let index = format!("const SEARCH_INDEX = {index};\n");
+    write(&out.join("search-index.js"), &index)?;
+    Ok(())
+}
+
+fn write(file: &Path, contents: &str) -> Result<(), SiteError> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| SiteError::CreateDir(dir.to_path_buf(), e))?;
+    }
+    fs::write(file, contents).map_err(|e| SiteError::Write(file.to_path_buf(), e))
+}
+
+struct Site<'a> {
+    model: &'a Model,
+    /// The args that refer to an interface, keyed by repo, protocol, and
+    /// interface name.
+    referenced_by: HashMap<(&'a str, &'a str, &'a str), Vec<Reference<'a>>>,
+    search: Vec<SearchEntry>,
+}
+
+struct Reference<'a> {
+    repo: &'a str,
+    protocol: &'a str,
+    interface: &'a str,
+    message: &'a Message,
+    arg: &'a Arg,
+}
+
+#[derive(Serialize)]
+struct SearchEntry {
+    name: String,
+    kind: &'static str,
+    /// The URL relative to the root of the site.
+    url: String,
+    summary: String,
+}
+
+impl<'a> Site<'a> {
+    fn collect_references(&mut self) {
+        let model = self.model;
+        for repo in &model.repos {
+            for protocol in &repo.protocols {
+                for interface in &protocol.interfaces {
+                    for message in &interface.messages {
+                        for arg in &message.args {
+                            let path = Model::arg_path(repo, protocol, interface, message, arg);
+                            let Some(targets) = model.arg_interfaces.get(&path) else {
+                                continue;
+                            };
+                            for target in targets {
+                                let key = (&*target.repo, &*target.protocol, &*target.interface);
+                                self.referenced_by.entry(key).or_default().push(Reference {
+                                    repo: &repo.name,
+                                    protocol: &protocol.name,
+                                    interface: &interface.name,
+                                    message,
+                                    arg,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn index_page(&self) -> String {
+        let mut body = String::new();
+        body.push_str(
+            "<h1>Wayland protocols</h1>\n\
+             <input id=\"search\" type=\"search\" placeholder=\"Search\" autofocus>\n\
+             <ul id=\"search-results\"></ul>\n",
+        );
+        for repo in &self.model.repos {
+            let _ = writeln!(
+                body,
+                "<h2 id=\"{name}\">{name}</h2>\n<p><a href=\"{url}\">{url}</a></p>\n<ul>",
+                name = escape(&repo.name),
+                url = escape(&repo.url),
+            );
+            for protocol in &repo.protocols {
+                let _ = writeln!(
+                    body,
+                    "<li><a href=\"{}/{}/index.html\">{}</a> {}</li>",
+                    escape(&repo.name),
+                    escape(&protocol.name),
+                    escape(&protocol.name),
+                    escape(Description::summary_of(&protocol.description).unwrap_or_default()),
+                );
+            }
+            body.push_str("</ul>\n");
+        }
+        body.push_str(
+            "<script src=\"search-index.js\"></script>\n\
+             <script src=\"search.js\"></script>\n",
+        );
+        page("Wayland protocols", "", &body)
+    }
+
+    fn protocol_page(&mut self, repo: &Repo, protocol: &Protocol) -> String {
+        let base = format!("{}/{}/", repo.name, protocol.name);
+        self.search.push(SearchEntry {
+            name: protocol.name.clone(),
+            kind: "protocol",
+            url: format!("{base}index.html"),
+            summary: Description::summary_of(&protocol.description)
+                .unwrap_or_default()
+                .to_string(),
+        });
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<nav><a href=\"../../index.html\">Index</a> › <a href=\"../../index.html#{repo}\">{repo}</a></nav>\n\
+             <h1>{}</h1>",
+            escape(&protocol.name),
+            repo = escape(&repo.name),
+        );
+        description(&mut body, &protocol.description);
+        body.push_str("<h2>Interfaces</h2>\n<ul>\n");
+        for interface in &protocol.interfaces {
+            let _ = writeln!(
+                body,
+                "<li><a href=\"{name}.html\">{name}</a> {} {}</li>",
+                badge("version", interface.version),
+                escape(Description::summary_of(&interface.description).unwrap_or_default()),
+                name = escape(&interface.name),
+            );
+        }
+        body.push_str("</ul>\n");
+        if let Some(copyright) = &protocol.copyright {
+            let _ = writeln!(
+                body,
+                "<h2>Copyright</h2>\n<pre>{}</pre>",
+                escape(copyright.body.trim_end())
+            );
+        }
+        page(&protocol.name, "../../", &body)
+    }
+
+    fn interface_page(
+        &mut self,
+        repo: &Repo,
+        protocol: &Protocol,
+        interface: &Interface,
+    ) -> String {
+        let base = format!("{}/{}/{}.html", repo.name, protocol.name, interface.name);
+        self.search.push(SearchEntry {
+            name: interface.name.clone(),
+            kind: "interface",
+            url: base.clone(),
+            summary: Description::summary_of(&interface.description)
+                .unwrap_or_default()
+                .to_string(),
+        });
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<nav><a href=\"../../index.html\">Index</a> › <a href=\"../../index.html#{repo}\">{repo}</a> › \
+             <a href=\"index.html\">{protocol}</a></nav>\n\
+             <h1>{} {}</h1>",
+            escape(&interface.name),
+            badge("version", interface.version),
+            repo = escape(&repo.name),
+            protocol = escape(&protocol.name),
+        );
+        description(&mut body, &interface.description);
+        for (is_request, title) in [(true, "Requests"), (false, "Events")] {
+            let messages: Vec<_> = interface.messages_of_kind(is_request).collect();
+            if messages.is_empty() {
+                continue;
+            }
+            let _ = writeln!(body, "<h2>{title}</h2>");
+            for message in messages {
+                self.message(&mut body, &base, repo, protocol, interface, message);
+            }
+        }
+        if !interface.enums.is_empty() {
+            body.push_str("<h2>Enums</h2>\n");
+            for enum_ in &interface.enums {
+                self.enum_(&mut body, &base, interface, enum_);
+            }
+        }
+        let key = (&*repo.name, &*protocol.name, &*interface.name);
+        if let Some(references) = self.referenced_by.get(&key) {
+            body.push_str("<h2>Referenced by</h2>\n<ul>\n");
+            for r in references {
+                let _ = writeln!(
+                    body,
+                    "<li><a href=\"../../{}/{}/{}.html#{}\">{}.{}</a> ({}) in {}</li>",
+                    escape(r.repo),
+                    escape(r.protocol),
+                    escape(r.interface),
+                    anchor(r.message),
+                    escape(r.interface),
+                    escape(&r.message.name),
+                    escape(&r.arg.name),
+                    escape(r.repo),
+                );
+            }
+            body.push_str("</ul>\n");
+        }
+        page(&interface.name, "../../", &body)
+    }
+
+    fn message(
+        &mut self,
+        body: &mut String,
+        base: &str,
+        repo: &Repo,
+        protocol: &Protocol,
+        interface: &Interface,
+        message: &Message,
+    ) {
+        let anchor = anchor(message);
+        let kind = match message.is_request {
+            true => "request",
+            false => "event",
+        };
+        self.search.push(SearchEntry {
+            name: format!("{}.{}", interface.name, message.name),
+            kind,
+            url: format!("{base}#{anchor}"),
+            summary: Description::summary_of(&message.description)
+                .unwrap_or_default()
+                .to_string(),
+        });
+        let _ = write!(
+            body,
+            "<section id=\"{anchor}\">\n<h3><a href=\"#{anchor}\">{}</a> {}",
+            escape(&message.name),
+            badge("opcode", message.message_id),
+        );
+        if let Some(since) = message.since {
+            let _ = write!(body, " {}", badge("since", since));
+        }
+        if let Some(deprecated_since) = message.deprecated_since {
+            let _ = write!(body, " {}", badge("deprecated since", deprecated_since));
+        }
+        if message.ty == Some(MessageType::Destructor) {
+            body.push_str(" <span class=\"badge\">destructor</span>");
+        }
+        body.push_str("</h3>\n");
+        description(body, &message.description);
+        if !message.args.is_empty() {
+            body.push_str("<table>\n<tr><th>Arg</th><th>Type</th><th>Description</th></tr>\n");
+            for arg in &message.args {
+                let _ = writeln!(
+                    body,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape(&arg.name),
+                    self.arg_type(repo, protocol, interface, message, arg),
+                    escape(arg.summary.as_deref().unwrap_or("")),
+                );
+            }
+            body.push_str("</table>\n");
+        }
+        body.push_str("</section>\n");
+    }
+
+    /// Returns the type of an arg with links to the resolved interfaces and
+    /// enums.
+    fn arg_type(
+        &self,
+        repo: &Repo,
+        protocol: &Protocol,
+        interface: &Interface,
+        message: &Message,
+        arg: &Arg,
+    ) -> String {
+        let path = Model::arg_path(repo, protocol, interface, message, arg);
+        let mut res = String::new();
+        if arg.allow_null {
+            res.push('?');
+        }
+        res.push_str(arg.ty.name());
+        if let Some(name) = &arg.interface {
+            let targets = self.model.arg_interfaces.get(&path).map(|v| &v[..]);
+            let links: Vec<_> = targets
+                .unwrap_or_default()
+                .iter()
+                .map(|t| {
+                    format!(
+                        "<a href=\"../../{}/{}/{}.html\" title=\"{}/{}\">{}</a>",
+                        escape(&t.repo),
+                        escape(&t.protocol),
+                        escape(&t.interface),
+                        escape(&t.repo),
+                        escape(&t.protocol),
+                        escape(&t.interface),
+                    )
+                })
+                .collect();
+            let _ = match links.is_empty() {
+                true => write!(res, "&lt;{}&gt;", escape(name)),
+                false => write!(res, "&lt;{}&gt;", links.join(" | ")),
+            };
+        }
+        if let Some(name) = &arg.enum_ {
+            let targets = self.model.arg_enums.get(&path).map(|v| &v[..]);
+            let links: Vec<_> = targets
+                .unwrap_or_default()
+                .iter()
+                .map(|t| {
+                    format!(
+                        "<a href=\"../../{}/{}/{}.html#enum-{}\" title=\"{}/{}\">{}</a>",
+                        escape(&t.repo),
+                        escape(&t.protocol),
+                        escape(&t.interface),
+                        escape(&t.enum_),
+                        escape(&t.repo),
+                        escape(&t.protocol),
+                        escape(name),
+                    )
+                })
+                .collect();
+            let _ = match links.is_empty() {
+                true => write!(res, " enum {}", escape(name)),
+                false => write!(res, " enum {}", links.join(" | ")),
+            };
+        }
+        res
+    }
+
+    fn enum_(&mut self, body: &mut String, base: &str, interface: &Interface, enum_: &Enum) {
+        let anchor = format!("enum-{}", escape(&enum_.name));
+        self.search.push(SearchEntry {
+            name: format!("{}.{}", interface.name, enum_.name),
+            kind: "enum",
+            url: format!("{base}#{anchor}"),
+            summary: Description::summary_of(&enum_.description)
+                .unwrap_or_default()
+                .to_string(),
+        });
+        let _ = write!(
+            body,
+            "<section id=\"{anchor}\">\n<h3><a href=\"#{anchor}\">{}</a>",
+            escape(&enum_.name),
+        );
+        if let Some(since) = enum_.since {
+            let _ = write!(body, " {}", badge("since", since));
+        }
+        if enum_.bitfield {
+            body.push_str(" <span class=\"badge\">bitfield</span>");
+        }
+        body.push_str("</h3>\n");
+        description(body, &enum_.description);
+        body.push_str("<table>\n<tr><th>Entry</th><th>Value</th><th>Description</th></tr>\n");
+        for entry in &enum_.entries {
+            let _ = write!(
+                body,
+                "<tr id=\"{anchor}-{name}\"><td>{name}",
+                name = escape(&entry.name),
+            );
+            if let Some(since) = entry.since {
+                let _ = write!(body, " {}", badge("since", since));
+            }
+            if let Some(deprecated_since) = entry.deprecated_since {
+                let _ = write!(body, " {}", badge("deprecated since", deprecated_since));
+            }
+            let _ = writeln!(
+                body,
+                "</td><td>{}</td><td>{}</td></tr>",
+                escape(&entry.value),
+                escape(entry.summary.as_deref().unwrap_or("")),
+            );
+        }
+        body.push_str("</table>\n</section>\n");
+    }
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{}</title>\n\
+         <link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+         </head>\n\
+         <body>\n\
+         {body}\
+         </body>\n\
+         </html>\n",
+        escape(title),
+    )
+}
+
+fn anchor(message: &Message) -> String {
+    let kind = match message.is_request {
+        true => "request",
+        false => "event",
+    };
+    format!("{kind}-{}", escape(&message.name))
+}
+
+fn badge(name: &str, value: impl std::fmt::Display) -> String {
+    let class = match name {
+        "deprecated since" => "badge deprecated",
+        _ => "badge",
+    };
+    format!("<span class=\"{class}\">{name} {value}</span>")
+}
+
+/// Writes the summary and the paragraphs of a description.
+fn description(body: &mut String, description: &Option<Description>) {
+    let Some(description) = description else {
+        return;
+    };
+    if let Some(summary) = &description.summary {
+        let _ = writeln!(body, "<p class=\"summary\">{}</p>", escape(summary));
+    }
+    for paragraph in description.body.split("\n\n") {
+        if !paragraph.trim().is_empty() {
+            let _ = writeln!(body, "<p>{}</p>", escape(paragraph.trim_end()));
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    escape_xml(s, true)
+}
@@ -0,0 +1,36 @@
+"use strict";
+
+(function () {
+    const input = document.getElementById("search");
+    const results = document.getElementById("search-results");
+    const limit = 100;
+
+    function update() {
+        const query = input.value.trim().toLowerCase();
+        results.replaceChildren();
+        if (query === "") {
+            return;
+        }
+        let count = 0;
+        for (const entry of SEARCH_INDEX) {
+            if (!entry.name.toLowerCase().includes(query)) {
+                continue;
+            }
+            const li = document.createElement("li");
+            const a = document.createElement("a");
+            a.href = entry.url;
+            a.textContent = entry.name;
+            const kind = document.createElement("span");
+            kind.className = "kind";
+            kind.textContent = " " + entry.kind + " ";
+            li.append(a, kind, entry.summary);
+            results.append(li);
+            if (++count === limit) {
+                break;
+            }
+        }
+    }
+
+    input.addEventListener("input", update);
+    update();
+})();
@@ -0,0 +1,94 @@
+//! Normalization and escaping of the text content of elements.
+
+/// Removes the indentation of multi-line text such as descriptions and
+/// copyright notices.
+///
+/// The indentation of the first non-empty line is removed from all lines, tabs
+/// are expanded to 8 columns, and leading and trailing empty lines are dropped.
+pub fn format_ml_text(description: &str) -> String {
+    let mut trim = None;
+    let mut empty_lines = 0;
+    let mut out = String::new();
+    'outer: for mut line in description.lines() {
+        if trim.is_none() {
+            let mut spaces = 0usize;
+            'spaces: {
+                for c in line.chars() {
+                    if c == ' ' {
+                        spaces += 1;
+                    } else if c == '\t' {
+                        spaces = (spaces + 8) & !7;
+                    } else {
+                        break 'spaces;
+                    }
+                }
+                continue 'outer;
+            }
+            trim = Some(spaces);
+        }
+        let trim = trim.unwrap();
+        let mut line_buf = String::new();
+        if line.contains('\t') {
+            let mut offset = 0;
+            for c in line.chars() {
+                if c == '\t' {
+                    line_buf.push(' ');
+                    offset += 1;
+                    let delta = (-offset) & 7;
+                    for _ in 0..delta {
+                        line_buf.push(' ');
+                    }
+                    offset += delta;
+                } else {
+                    line_buf.push(c);
+                    offset += 1;
+                }
+            }
+            line = &line_buf;
+        }
+        let idx = 'idx: {
+            let mut spaces = 0usize;
+            for (idx, c) in line.char_indices() {
+                if c == ' ' {
+                    spaces += 1;
+                } else {
+                    break 'idx idx;
+                }
+                if spaces >= trim {
+                    break 'idx idx + 1;
+                }
+            }
+            line.len()
+        };
+        line = &line[idx..];
+        if line.trim_ascii().is_empty() {
+            empty_lines += 1;
+            continue;
+        }
+        if empty_lines > 0 {
+            for _ in 0..empty_lines {
+                out.push('\n');
+            }
+            empty_lines = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Escapes text content or, if `attr` is true, a quoted attribute value for XML
+/// or HTML.
+pub fn escape_xml(s: &str, attr: bool) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' if attr => res.push_str("&quot;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
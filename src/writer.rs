@@ -0,0 +1,388 @@
+use {
+    crate::{
+        ast::{
+            Arg, Description, Entry, Enum, Interface, InterfaceElement, Message, MessageType,
+            Protocol,
+        },
+        text::{escape_xml, format_ml_text},
+    },
+    std::{fmt::Write, mem},
+};
+
+const INDENT: &str = "  ";
+
+/// Serializes a protocol as canonical XML.
+///
+/// Elements are indented by two spaces and attributes are written in a fixed
+/// order. Text content is normalized with
+/// [`format_ml_text`] and indented one level deeper than its element. The
+/// messages and enums of an interface are written in the order of
+/// [`Interface::elements`].
+///
+/// Parsing the output with [`parse`](crate::parser::parse) returns the same
+/// protocol except for the normalization of text content.
+pub fn write(protocol: &Protocol) -> String {
+    let mut w = Writer { out: String::new() };
+    w.out
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    w.protocol(protocol);
+    w.out
+}
+
+struct Writer {
+    out: String,
+}
+
+impl Writer {
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn open(&mut self, depth: usize, name: &str, attrs: &[(&str, Option<&str>)]) {
+        self.indent(depth);
+        self.out.push('<');
+        self.out.push_str(name);
+        for &(name, value) in attrs {
+            if let Some(value) = value {
+                // Parsers replace newlines and tabs in attribute values with spaces.
+                let value = escape_xml(value, true)
+                    .replace('\n', "&#10;")
+                    .replace('\t', "&#9;");
+                let _ = write!(self.out, " {name}=\"{value}\"");
+            }
+        }
+    }
+
+    fn start(&mut self, depth: usize, name: &str, attrs: &[(&str, Option<&str>)]) {
+        self.open(depth, name, attrs);
+        self.out.push_str(">\n");
+    }
+
+    fn empty(&mut self, depth: usize, name: &str, attrs: &[(&str, Option<&str>)]) {
+        self.open(depth, name, attrs);
+        self.out.push_str("/>\n");
+    }
+
+    fn end(&mut self, depth: usize, name: &str) {
+        self.indent(depth);
+        let _ = writeln!(self.out, "</{name}>");
+    }
+
+    /// Writes an element whose content is multi-line text.
+    fn text(&mut self, depth: usize, name: &str, attrs: &[(&str, Option<&str>)], text: &str) {
+        let text = format_ml_text(text);
+        if text.is_empty() {
+            self.empty(depth, name, attrs);
+            return;
+        }
+        self.start(depth, name, attrs);
+        for line in text.lines() {
+            let line = line.trim_end();
+            if !line.is_empty() {
+                self.indent(depth + 1);
+                self.out.push_str(&escape_xml(line, false));
+            }
+            self.out.push('\n');
+        }
+        self.end(depth, name);
+    }
+
+    fn description(&mut self, depth: usize, description: &Description) {
+        let attrs = [("summary", description.summary.as_deref())];
+        self.text(depth, "description", &attrs, &description.body);
+    }
+
+    fn protocol(&mut self, protocol: &Protocol) {
+        self.start(0, "protocol", &[("name", Some(&protocol.name))]);
+        let mut first = true;
+        let mut separate = |w: &mut Self| {
+            if !std::mem::take(&mut first) {
+                w.out.push('\n');
+            }
+        };
+        if let Some(copyright) = &protocol.copyright {
+            separate(self);
+            self.text(1, "copyright", &[], &copyright.body);
+        }
+        if let Some(description) = &protocol.description {
+            separate(self);
+            self.description(1, description);
+        }
+        for interface in &protocol.interfaces {
+            separate(self);
+            self.interface(interface);
+        }
+        self.end(0, "protocol");
+    }
+
+    fn interface(&mut self, interface: &Interface) {
+        let version = interface.version.to_string();
+        let attrs = [
+            ("name", Some(&*interface.name)),
+            ("version", Some(&*version)),
+        ];
+        if interface.description.is_none()
+            && interface.messages.is_empty()
+            && interface.enums.is_empty()
+        {
+            self.empty(1, "interface", &attrs);
+            return;
+        }
+        self.start(1, "interface", &attrs);
+        let mut first = true;
+        let mut separate = |w: &mut Self| {
+            if !std::mem::take(&mut first) {
+                w.out.push('\n');
+            }
+        };
+        if let Some(description) = &interface.description {
+            separate(self);
+            self.description(2, description);
+        }
+        for element in elements(interface) {
+            separate(self);
+            match element {
+                InterfaceElement::Message(idx) => self.message(&interface.messages[idx]),
+                InterfaceElement::Enum(idx) => self.enum_(&interface.enums[idx]),
+            }
+        }
+        self.end(1, "interface");
+    }
+
+    fn message(&mut self, message: &Message) {
+        let name = match message.is_request {
+            true => "request",
+            false => "event",
+        };
+        let since = message.since.map(|v| v.to_string());
+        let deprecated_since = message.deprecated_since.map(|v| v.to_string());
+        let attrs = [
+            ("name", Some(&*message.name)),
+            (
+                "type",
+                match message.ty {
+                    Some(MessageType::Destructor) => Some("destructor"),
+                    None => None,
+                },
+            ),
+            ("since", since.as_deref()),
+            ("deprecated-since", deprecated_since.as_deref()),
+        ];
+        if message.description.is_none() && message.args.is_empty() {
+            self.empty(2, name, &attrs);
+            return;
+        }
+        self.start(2, name, &attrs);
+        if let Some(description) = &message.description {
+            self.description(3, description);
+        }
+        for arg in &message.args {
+            self.arg(arg);
+        }
+        self.end(2, name);
+    }
+
+    fn arg(&mut self, arg: &Arg) {
+        let attrs = [
+            ("name", Some(&*arg.name)),
+            ("type", Some(arg.ty.name())),
+            ("interface", arg.interface.as_deref()),
+            ("allow-null", arg.allow_null.then_some("true")),
+            ("enum", arg.enum_.as_deref()),
+            ("summary", arg.summary.as_deref()),
+        ];
+        match &arg.description {
+            None => self.empty(3, "arg", &attrs),
+            Some(description) => {
+                self.start(3, "arg", &attrs);
+                self.description(4, description);
+                self.end(3, "arg");
+            }
+        }
+    }
+
+    fn enum_(&mut self, enum_: &Enum) {
+        let since = enum_.since.map(|v| v.to_string());
+        let attrs = [
+            ("name", Some(&*enum_.name)),
+            ("since", since.as_deref()),
+            ("bitfield", enum_.bitfield.then_some("true")),
+        ];
+        if enum_.description.is_none() && enum_.entries.is_empty() {
+            self.empty(2, "enum", &attrs);
+            return;
+        }
+        self.start(2, "enum", &attrs);
+        if let Some(description) = &enum_.description {
+            self.description(3, description);
+        }
+        for entry in &enum_.entries {
+            self.entry(entry);
+        }
+        self.end(2, "enum");
+    }
+
+    fn entry(&mut self, entry: &Entry) {
+        let since = entry.since.map(|v| v.to_string());
+        let deprecated_since = entry.deprecated_since.map(|v| v.to_string());
+        let attrs = [
+            ("name", Some(&*entry.name)),
+            ("value", Some(&*entry.value)),
+            ("summary", entry.summary.as_deref()),
+            ("since", since.as_deref()),
+            ("deprecated-since", deprecated_since.as_deref()),
+        ];
+        match &entry.description {
+            None => self.empty(3, "entry", &attrs),
+            Some(description) => {
+                self.start(3, "entry", &attrs);
+                self.description(4, description);
+                self.end(3, "entry");
+            }
+        }
+    }
+}
+
+/// Returns [`Interface::elements`] if it contains every message and enum exactly
+/// once. Otherwise, e.g. if the interface was loaded from the database, the
+/// messages are returned before the enums.
+fn elements(interface: &Interface) -> Vec<InterfaceElement> {
+    let mut messages = vec![false; interface.messages.len()];
+    let mut enums = vec![false; interface.enums.len()];
+    let complete = interface.elements.len() == messages.len() + enums.len()
+        && interface.elements.iter().all(|element| {
+            let seen = match *element {
+                InterfaceElement::Message(idx) => messages.get_mut(idx),
+                InterfaceElement::Enum(idx) => enums.get_mut(idx),
+            };
+            seen.is_some_and(|seen| !mem::replace(seen, true))
+        });
+    if complete {
+        return interface.elements.clone();
+    }
+    let messages = (0..messages.len()).map(InterfaceElement::Message);
+    let enums = (0..enums.len()).map(InterfaceElement::Enum);
+    messages.chain(enums).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::write,
+        crate::{ast::Protocol, parser::parse, text::format_ml_text},
+        serde_json::Value,
+        std::{fs, path::Path},
+    };
+
+    /// Returns the protocols as JSON with normalized text content.
+    fn normalized(protocols: &[Protocol]) -> Value {
+        fn normalize(value: &mut Value) {
+            match value {
+                Value::Array(values) => values.iter_mut().for_each(normalize),
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        match (key.as_str(), &mut *value) {
+                            ("body", Value::String(body)) => *body = format_ml_text(body),
+                            _ => normalize(value),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut value = serde_json::to_value(protocols).unwrap();
+        normalize(&mut value);
+        value
+    }
+
+    /// Checks that parsing the output of `write` returns the same protocols and
+    /// that writing them again returns the same output.
+    fn round_trip(input: &str) {
+        let path = Path::new("test.xml");
+        let protocols = parse(path, input.as_bytes()).unwrap();
+        let output: String = protocols.iter().map(write).collect();
+        let reparsed = parse(path, output.as_bytes()).unwrap();
+        assert_eq!(normalized(&protocols), normalized(&reparsed), "{output}");
+        let rewritten: String = reparsed.iter().map(write).collect();
+        assert_eq!(output, rewritten);
+    }
+
+    #[test]
+    fn fixtures() {
+        for name in ["wayland.xml", "xdg-shell.xml"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/data")
+                .join(name);
+            round_trip(&fs::read_to_string(path).unwrap());
+        }
+    }
+
+    #[test]
+    fn entities_in_attributes() {
+        round_trip(
+            r#"<protocol name="p">
+                 <interface name="i" version="1">
+                   <request name="r">
+                     <arg name="a" type="int" summary="a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos; &#x263A;"/>
+                     <arg name="b" type="int" summary="line&#10;break and&#9;tab"/>
+                   </request>
+                 </interface>
+               </protocol>"#,
+        );
+    }
+
+    #[test]
+    fn entities_in_text() {
+        round_trip(
+            r#"<protocol name="p">
+                 <copyright>
+                   Copyright &#169; 2024 A &amp; B
+                 </copyright>
+                 <description summary="s">
+                   a &amp; b, a &lt; b, a &gt; b, "quoted" &amp;amp;
+                 </description>
+               </protocol>"#,
+        );
+    }
+
+    #[test]
+    fn tabs() {
+        round_trip(
+            "<protocol name=\"p\">\n\
+             \t<interface name=\"i\" version=\"1\">\n\
+             \t\t<description summary=\"s\">\n\
+             \t\t\tfirst line\n\
+             \t\t\t\tindented\twith a tab\n\
+             \n\
+             \t\t\tsecond paragraph\n\
+             \t\t</description>\n\
+             \t</interface>\n\
+             </protocol>",
+        );
+    }
+
+    #[test]
+    fn empty_elements() {
+        round_trip(
+            r#"<protocol name="p">
+                 <copyright/>
+                 <description summary="s"/>
+                 <interface name="a" version="1"/>
+                 <interface name="b" version="2">
+                   <description summary="s"></description>
+                   <enum name="f"/>
+                   <request name="r"/>
+                   <event name="e" since="2"/>
+                   <enum name="g" bitfield="true">
+                     <entry name="x" value="1"/>
+                     <entry name="y" value="0x2">
+                       <description summary="y"/>
+                     </entry>
+                   </enum>
+                 </interface>
+               </protocol>"#,
+        );
+    }
+}